@@ -0,0 +1,49 @@
+use aoc_2020::day11::{solve_part1, solve_part1_automaton, solve_part2, solve_part2_automaton};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs::File;
+use std::io::Read;
+
+fn bench_day11_solve_part1(c: &mut Criterion) {
+    let mut file = File::open("input/2020/day11.txt").unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+
+    c.bench_function("day11 part1", |b| b.iter(|| solve_part1(&input)));
+}
+
+fn bench_day11_solve_part1_automaton(c: &mut Criterion) {
+    let mut file = File::open("input/2020/day11.txt").unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+
+    c.bench_function("day11 part1 automaton", |b| {
+        b.iter(|| solve_part1_automaton(&input))
+    });
+}
+
+fn bench_day11_solve_part2(c: &mut Criterion) {
+    let mut file = File::open("input/2020/day11.txt").unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+
+    c.bench_function("day11 part2", |b| b.iter(|| solve_part2(&input)));
+}
+
+fn bench_day11_solve_part2_automaton(c: &mut Criterion) {
+    let mut file = File::open("input/2020/day11.txt").unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+
+    c.bench_function("day11 part2 automaton", |b| {
+        b.iter(|| solve_part2_automaton(&input))
+    });
+}
+
+criterion_group!(
+    day11_benches,
+    bench_day11_solve_part1,
+    bench_day11_solve_part1_automaton,
+    bench_day11_solve_part2,
+    bench_day11_solve_part2_automaton,
+);
+criterion_main!(day11_benches);
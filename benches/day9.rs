@@ -0,0 +1,31 @@
+use aoc_2020::day9::{parse_part1, solve_part1, solve_part1_windows};
+use criterion::{criterion_group, criterion_main, Criterion};
+use std::fs::File;
+use std::io::Read;
+
+fn bench_day9_solve_part1(c: &mut Criterion) {
+    let mut file = File::open("input/2020/day9.txt").unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+    let input = parse_part1(&input);
+
+    c.bench_function("day9 part1", |b| b.iter(|| solve_part1(&input)));
+}
+
+fn bench_day9_solve_part1_windows(c: &mut Criterion) {
+    let mut file = File::open("input/2020/day9.txt").unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+    let input = parse_part1(&input);
+
+    c.bench_function("day9 part1 windows", |b| {
+        b.iter(|| solve_part1_windows(&input))
+    });
+}
+
+criterion_group!(
+    day9_benches,
+    bench_day9_solve_part1,
+    bench_day9_solve_part1_windows
+);
+criterion_main!(day9_benches);
@@ -1,4 +1,5 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 
@@ -6,39 +7,256 @@ pub fn parse_input(input: &str) -> HashSet<usize> {
     input.lines().map(|line| line.parse().unwrap()).collect()
 }
 
-pub fn solve_part1(input: &HashSet<usize>) -> usize {
-    let mut current_jolt = 0;
-    let mut diff_map: [usize; 3] = [0; 3];
-    while &current_jolt != input.iter().max().unwrap() {
-        match find_adapter_for_jolt(input, current_jolt) {
-            Some((diff, next_jolt)) => {
-                current_jolt = next_jolt;
-                diff_map[diff - 1] += 1;
+#[derive(PartialEq, Debug, Clone)]
+pub struct AdapterChain {
+    pub chain: Vec<usize>, // the outlet, every adapter in order, then the device
+    pub diffs: [usize; 3], // number of 1, 2 and 3 jolts differences along the chain
+}
+
+impl AdapterChain {
+    pub fn device(&self) -> usize {
+        *self.chain.last().unwrap()
+    }
+}
+
+// uses every adapter of the bag, failing on the first difference over 3 jolts;
+// an adapter rated 0 is the outlet itself, like in `AdapterSet`
+pub fn build_chain(adapters: &HashSet<usize>) -> Result<AdapterChain, AdapterError> {
+    let mut chain = vec![0];
+    chain.extend(adapters.iter().copied().filter(|adapter| *adapter != 0));
+    chain.sort_unstable();
+    let device = chain[chain.len() - 1]
+        .checked_add(3)
+        .ok_or(AdapterError::Overflow)?;
+    chain.push(device);
+
+    let mut diffs = [0; 3];
+    for pair in chain.windows(2) {
+        match pair[1] - pair[0] {
+            diff @ 1..=3 => diffs[diff - 1] += 1,
+            _ => {
+                return Err(AdapterError::Gap {
+                    from: pair[0],
+                    to: pair[1],
+                })
             }
-            None => panic!("failed to find adapter for jolt {}", current_jolt),
         }
     }
-    diff_map[0] * (diff_map[2] + 1)
+
+    Ok(AdapterChain { chain, diffs })
+}
+
+pub fn solve_part1(input: &HashSet<usize>) -> usize {
+    let chain = build_chain(input).unwrap();
+    chain.diffs[0] * chain.diffs[2]
 }
 
 pub fn find_adapter_for_jolt(adapters: &HashSet<usize>, jolt: usize) -> Option<(usize, usize)> {
     (1..=3).find_map(|diff| Some((diff, *adapters.get(&(jolt + diff))?)))
 }
 
-fn tribonnaci(input: usize) -> usize {
-    match input {
-        0 => 1,
-        1 => 1,
-        2 => 2,
-        _ => tribonnaci(input - 1) + tribonnaci(input - 2) + tribonnaci(input - 3),
+#[derive(PartialEq, Debug, Clone)]
+pub enum AdapterError {
+    Gap { from: usize, to: usize }, // no adapter in between can take `from` jolts
+    Overflow,                       // device rating or arrangement count past the integer range
+}
+
+impl fmt::Display for AdapterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AdapterError::Gap { from, to } => write!(
+                f,
+                "no adapter takes {} jolts, the next one is rated {} jolts",
+                from, to
+            ),
+            AdapterError::Overflow => write!(f, "joltage or arrangement count too large"),
+        }
     }
 }
 
-pub fn solve_part2(input: &[usize]) -> usize {
-    input
-        .split(|num| *num == 3)
-        .map(|sub| tribonnaci(sub.len()))
-        .product()
+// chains from the outlet to the device, every step being one of `allowed_jumps`
+pub fn count_arrangements(
+    adapters: &HashSet<usize>,
+    allowed_jumps: &[usize],
+) -> Result<u128, AdapterError> {
+    AdapterSet::new(adapters, allowed_jumps).count()
+}
+
+pub fn solve_part2(input: &HashSet<usize>) -> u128 {
+    count_arrangements(input, &[1, 2, 3]).unwrap()
+}
+
+// the adapters as a graph from the outlet to the device, rated 3 jolts above the
+// highest adapter, with the number of chains from every adapter to the device
+// (`None` when it overflows)
+#[derive(PartialEq, Debug, Clone)]
+pub struct AdapterSet {
+    jolts: Vec<usize>,           // the outlet, the adapters sorted, then the device
+    successors: Vec<Vec<usize>>, // indexes in `jolts`, by increasing joltage
+    ways_to_end: Vec<Option<u128>>,
+}
+
+impl AdapterSet {
+    pub fn new(adapters: &HashSet<usize>, allowed_jumps: &[usize]) -> AdapterSet {
+        let mut jolts = vec![0];
+        jolts.extend(adapters.iter().copied().filter(|adapter| *adapter != 0));
+        jolts.sort_unstable();
+        let device = jolts[jolts.len() - 1].checked_add(3);
+        jolts.extend(device);
+        let index = jolts
+            .iter()
+            .enumerate()
+            .map(|(i, jolt)| (*jolt, i))
+            .collect::<HashMap<_, _>>();
+
+        let mut jumps = allowed_jumps
+            .iter()
+            .copied()
+            .filter(|jump| *jump > 0)
+            .collect::<Vec<_>>();
+        jumps.sort_unstable();
+        jumps.dedup();
+        let mut successors = jolts
+            .iter()
+            .map(|jolt| {
+                jumps
+                    .iter()
+                    .filter_map(|jump| index.get(&jolt.checked_add(*jump)?).copied())
+                    .collect()
+            })
+            .collect::<Vec<Vec<usize>>>();
+
+        let mut ways_to_end = vec![Some(0); jolts.len()];
+        ways_to_end[jolts.len() - 1] = Some(1);
+        for i in (0..jolts.len() - 1).rev() {
+            ways_to_end[i] = successors[i]
+                .iter()
+                .try_fold(0u128, |acc, next| acc.checked_add(ways_to_end[*next]?));
+        }
+        // no device can be rated that high: nothing to arrange
+        if device.is_none() {
+            successors.iter_mut().for_each(Vec::clear);
+            ways_to_end[0] = None;
+        }
+
+        AdapterSet {
+            jolts,
+            successors,
+            ways_to_end,
+        }
+    }
+
+    fn end(&self) -> usize {
+        self.jolts.len() - 1
+    }
+
+    pub fn count(&self) -> Result<u128, AdapterError> {
+        self.ways_to_end[0].ok_or(AdapterError::Overflow)
+    }
+
+    // every arrangement, as the adapters it uses (device excluded), in lexicographic order
+    pub fn arrangements(&self) -> Arrangements<'_> {
+        Arrangements {
+            set: self,
+            stack: vec![(0, 0)],
+        }
+    }
+
+    // the arrangement `arrangements()` would yield at position `k`
+    pub fn nth_arrangement(&self, mut k: u128) -> Option<Vec<usize>> {
+        if k >= self.count().ok()? {
+            return None;
+        }
+        let mut arrangement = Vec::new();
+        let mut current = 0;
+        while current != self.end() {
+            for next in &self.successors[current] {
+                let ways = self.ways_to_end[*next]?;
+                if k < ways {
+                    current = *next;
+                    break;
+                }
+                k -= ways;
+            }
+            if current != self.end() {
+                arrangement.push(self.jolts[current]);
+            }
+        }
+        Some(arrangement)
+    }
+
+    // an arrangement picked uniformly among all of them
+    pub fn sample(&self, rng: &mut XorShift) -> Option<Vec<usize>> {
+        let count = self.count().ok()?;
+        if count == 0 {
+            return None;
+        }
+        // reject the values past the last multiple of `count` to keep the draw uniform
+        let zone = u128::MAX - u128::MAX % count;
+        loop {
+            let random = (rng.next_u64() as u128) << 64 | rng.next_u64() as u128;
+            if random < zone {
+                return self.nth_arrangement(random % count);
+            }
+        }
+    }
+}
+
+pub struct Arrangements<'a> {
+    set: &'a AdapterSet,
+    stack: Vec<(usize, usize)>, // (index in `jolts`, next successor to explore)
+}
+
+impl<'a> Iterator for Arrangements<'a> {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        while let Some(&(current, next)) = self.stack.last() {
+            if current == self.set.end() && next == 0 {
+                self.stack.last_mut().unwrap().1 = 1;
+                return Some(
+                    self.stack[1..self.stack.len() - 1]
+                        .iter()
+                        .map(|(i, _)| self.set.jolts[*i])
+                        .collect(),
+                );
+            }
+            match self.set.successors[current].get(next) {
+                Some(successor) => {
+                    self.stack.last_mut().unwrap().1 += 1;
+                    // don't walk into adapters that can't reach the end
+                    if self.set.ways_to_end[*successor] != Some(0) {
+                        self.stack.push((*successor, 0));
+                    }
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+        None
+    }
+}
+
+// xorshift64*, enough to sample arrangements without pulling a dependency
+#[derive(PartialEq, Debug, Clone)]
+pub struct XorShift(u64);
+
+impl XorShift {
+    pub fn new(seed: u64) -> XorShift {
+        XorShift(if seed == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            seed
+        })
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
 }
 
 pub fn parse_slice_of_diffs(input: &str) -> Vec<usize> {
@@ -71,7 +289,7 @@ pub fn part2() {
     let mut file = File::open("input/2020/day10.txt").unwrap();
     let mut input = String::new();
     file.read_to_string(&mut input).unwrap();
-    println!("{}", solve_part2(&parse_slice_of_diffs(&input)));
+    println!("{}", solve_part2(&parse_input(&input)));
 }
 
 #[cfg(test)]
@@ -94,4 +312,142 @@ mod test {
             vec![1, 3, 1, 1, 1, 3, 1, 1, 3, 1, 3, 3]
         );
     }
+
+    #[test]
+    fn test_solve_part2_examples() {
+        assert_eq!(
+            solve_part2(&parse_input("16\n10\n15\n5\n1\n11\n7\n19\n6\n12\n4")),
+            8
+        );
+        assert_eq!(solve_part2(&parse_input("28\n33\n18\n42\n31\n14\n46\n20\n48\n47\n24\n23\n49\n45\n19\n38\n39\n11\n1\n32\n25\n35\n8\n17\n7\n9\n4\n2\n34\n10\n3")), 19208);
+    }
+
+    #[test]
+    fn test_count_arrangements_with_other_jumps() {
+        // a diff of 2 breaks the split-at-3 trick, which would count 4 chains here
+        let adapters = [1, 2, 4].iter().copied().collect::<HashSet<_>>();
+        assert_eq!(count_arrangements(&adapters, &[1, 2, 3]), Ok(3));
+        assert_eq!(count_arrangements(&adapters, &[2, 3]), Ok(1));
+        assert_eq!(count_arrangements(&adapters, &[3]), Ok(0));
+        // the device is 3 jolts above the highest adapter, out of reach here
+        assert_eq!(count_arrangements(&adapters, &[1, 2]), Ok(0));
+        assert_eq!(count_arrangements(&HashSet::new(), &[1, 2]), Ok(0));
+        assert_eq!(count_arrangements(&HashSet::new(), &[1, 2, 3]), Ok(1));
+    }
+
+    #[test]
+    fn test_count_arrangements_edge_cases() {
+        let adapters = [1, 2].iter().copied().collect::<HashSet<_>>();
+        // duplicated jumps are a single way to reach the next adapter
+        assert_eq!(count_arrangements(&adapters, &[1, 1, 3]), Ok(1));
+        assert_eq!(count_arrangements(&adapters, &[2, 1, 3, 2, 1, 3]), Ok(2));
+        // an adapter rated 0 is the outlet itself
+        let with_zero = [0, 1, 2].iter().copied().collect::<HashSet<_>>();
+        assert_eq!(count_arrangements(&with_zero, &[1, 2, 3]), Ok(2));
+        // jumps past usize::MAX lead nowhere
+        let one = [1].iter().copied().collect::<HashSet<_>>();
+        assert_eq!(count_arrangements(&one, &[3, usize::MAX]), Ok(0));
+        assert_eq!(count_arrangements(&one, &[1, 3, usize::MAX]), Ok(1));
+        // nor can the device be rated past it
+        let highest = [usize::MAX].iter().copied().collect::<HashSet<_>>();
+        assert_eq!(
+            count_arrangements(&highest, &[1, 2, 3]),
+            Err(AdapterError::Overflow)
+        );
+        let set = AdapterSet::new(&highest, &[1, 2, 3]);
+        assert_eq!(set.arrangements().next(), None);
+        assert_eq!(set.nth_arrangement(0), None);
+    }
+
+    #[test]
+    fn test_count_arrangements_overflow() {
+        let adapters = (1..=100).collect::<HashSet<_>>();
+        assert!(count_arrangements(&adapters, &[1, 2, 3]).is_ok());
+        assert_eq!(
+            count_arrangements(&(1..=200).collect(), &(1..=200).collect::<Vec<_>>()),
+            Err(AdapterError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_solve_part1_example() {
+        assert_eq!(
+            solve_part1(&parse_input("16\n10\n15\n5\n1\n11\n7\n19\n6\n12\n4")),
+            35
+        );
+    }
+
+    #[test]
+    fn test_build_chain() {
+        let chain = build_chain(&parse_input("16\n10\n15\n5\n1\n11\n7\n19\n6\n12\n4")).unwrap();
+        assert_eq!(
+            chain.chain,
+            vec![0, 1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19, 22]
+        );
+        assert_eq!(chain.diffs, [7, 0, 5]);
+        assert_eq!(chain.device(), 22);
+        assert_eq!(
+            build_chain(&parse_input("1\n2\n6\n7")),
+            Err(AdapterError::Gap { from: 2, to: 6 })
+        );
+        assert_eq!(
+            build_chain(&parse_input("4")),
+            Err(AdapterError::Gap { from: 0, to: 4 })
+        );
+        assert_eq!(
+            build_chain(&parse_input("0\n1\n3")).unwrap().chain,
+            vec![0, 1, 3, 6]
+        );
+        assert_eq!(build_chain(&parse_input("0")).unwrap().chain, vec![0, 3]);
+        assert_eq!(
+            build_chain(&[usize::MAX].iter().copied().collect()),
+            Err(AdapterError::Overflow)
+        );
+    }
+
+    #[test]
+    fn test_enumerate_arrangements() {
+        let set = AdapterSet::new(
+            &parse_input("16\n10\n15\n5\n1\n11\n7\n19\n6\n12\n4"),
+            &[1, 2, 3],
+        );
+        assert_eq!(set.count(), Ok(8));
+        let arrangements = set.arrangements().collect::<Vec<_>>();
+        assert_eq!(arrangements.len(), 8);
+        assert_eq!(arrangements[0], vec![1, 4, 5, 6, 7, 10, 11, 12, 15, 16, 19]);
+        assert_eq!(arrangements[7], vec![1, 4, 7, 10, 12, 15, 16, 19]);
+        assert!(arrangements.windows(2).all(|pair| pair[0] < pair[1]));
+        for (k, arrangement) in arrangements.iter().enumerate() {
+            assert_eq!(set.nth_arrangement(k as u128).as_ref(), Some(arrangement));
+        }
+        assert_eq!(set.nth_arrangement(8), None);
+    }
+
+    #[test]
+    fn test_enumerate_skips_dead_ends() {
+        let set = AdapterSet::new(&[1, 2, 6, 7].iter().copied().collect(), &[1, 2, 3]);
+        assert_eq!(set.count(), Ok(0));
+        assert_eq!(set.arrangements().next(), None);
+        let set = AdapterSet::new(&HashSet::new(), &[1, 2, 3]);
+        assert_eq!(set.arrangements().collect::<Vec<_>>(), vec![vec![]]);
+    }
+
+    #[test]
+    fn test_sample_arrangements() {
+        let set = AdapterSet::new(
+            &parse_input("16\n10\n15\n5\n1\n11\n7\n19\n6\n12\n4"),
+            &[1, 2, 3],
+        );
+        let arrangements = set.arrangements().collect::<Vec<_>>();
+        let mut rng = XorShift::new(42);
+        let mut seen = HashSet::new();
+        for _ in 0..1000 {
+            let sample = set.sample(&mut rng).unwrap();
+            seen.insert(arrangements.iter().position(|a| *a == sample).unwrap());
+        }
+        assert_eq!(seen.len(), 8);
+
+        let set = AdapterSet::new(&[1, 5].iter().copied().collect(), &[1, 2, 3]);
+        assert_eq!(set.sample(&mut rng), None);
+    }
 }
@@ -1,41 +1,49 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, Read, Write};
+use std::mem;
+use std::thread;
+use std::time::Duration;
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub enum GridElement {
     Ground,
     Empty,
     Occupied,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+const DIRECTIONS: [(isize, isize); 8] = [
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+    (0, -1),
+    (0, 1),
+    (1, -1),
+    (1, 0),
+    (1, 1),
+];
+
+#[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub struct Map {
     map: Vec<Vec<GridElement>>,
 }
 
 impl Map {
-    pub fn step_part1(&self) -> Map {
-        let mut new_map = self.clone();
-
-        for (y, line) in self.map.iter().enumerate() {
-            for (x, elem) in line.iter().enumerate() {
-                match elem {
-                    GridElement::Empty => {
-                        if self.is_seat_free_to_take_part1(y, x) {
-                            new_map.map[y][x] = GridElement::Occupied;
-                        }
-                    }
-                    GridElement::Occupied => {
-                        if self.is_seat_too_crowded_part1(y, x) {
-                            new_map.map[y][x] = GridElement::Empty;
-                        }
-                    }
-                    _ => (),
-                }
-            }
-        }
+    pub fn height(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.map.first().map_or(0, |line| line.len())
+    }
+
+    pub fn get(&self, y: usize, x: usize) -> Option<&GridElement> {
+        self.map.get(y)?.get(x)
+    }
 
-        new_map
+    pub fn step_part1(&self) -> Map {
+        Automaton::new(self, &Adjacent, SeatingRule { tolerance: 4 }).step(self)
     }
 
     pub fn is_seat_free_to_take_part1(&self, y: usize, x: usize) -> bool {
@@ -63,45 +71,43 @@ impl Map {
     }
 
     pub fn step_part2(&self) -> Map {
-        let mut new_map = self.clone();
-
-        for (y, line) in self.map.iter().enumerate() {
-            for (x, elem) in line.iter().enumerate() {
-                match elem {
-                    GridElement::Empty => {
-                        if self.is_seat_free_to_take_part2(y, x) {
-                            new_map.map[y][x] = GridElement::Occupied;
-                        }
-                    }
-                    GridElement::Occupied => {
-                        if self.is_seat_too_crowded_part2(y, x) {
-                            new_map.map[y][x] = GridElement::Empty;
-                        }
+        Automaton::new(self, &LineOfSight, SeatingRule { tolerance: 5 }).step(self)
+    }
+
+    // the first seat seen in each of the 8 directions, if any
+    pub fn visible_seats(&self, y: usize, x: usize) -> Vec<(usize, usize)> {
+        DIRECTIONS
+            .iter()
+            .filter_map(|(dy, dx)| {
+                let (mut y, mut x) = (y as isize + dy, x as isize + dx);
+                while y >= 0
+                    && x >= 0
+                    && (y as usize) < self.map.len()
+                    && (x as usize) < self.map[y as usize].len()
+                {
+                    if self.map[y as usize][x as usize] != GridElement::Ground {
+                        return Some((y as usize, x as usize));
                     }
-                    _ => (),
+                    y += dy;
+                    x += dx;
                 }
-            }
-        }
-
-        new_map
+                None
+            })
+            .collect()
     }
 
-    // TODO: PART2
     pub fn is_seat_free_to_take_part2(&self, y: usize, x: usize) -> bool {
-        false
+        self.visible_seats(y, x)
+            .iter()
+            .all(|(y, x)| self.map[*y][*x] != GridElement::Occupied)
     }
 
     pub fn is_seat_too_crowded_part2(&self, y: usize, x: usize) -> bool {
-        self.map[y - 1..=y + 1]
+        self.visible_seats(y, x)
             .iter()
-            .map(|line| {
-                line[x - 1..=x + 1]
-                    .iter()
-                    .filter(|elem| **elem == GridElement::Occupied)
-                    .count()
-            })
-            .sum::<usize>()
-            >= 6
+            .filter(|(y, x)| self.map[*y][*x] == GridElement::Occupied)
+            .count()
+            >= 5
     }
 
     pub fn count_empty_seats(&self) -> usize {
@@ -127,6 +133,202 @@ impl Map {
     }
 }
 
+// the map as parsed, without the ground added around it
+impl fmt::Display for Map {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.map[1..self.map.len().saturating_sub(1)] {
+            for elem in &line[1..line.len() - 1] {
+                write!(f, "{}", elem.symbol())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl GridElement {
+    pub fn symbol(&self) -> char {
+        match self {
+            GridElement::Ground => '.',
+            GridElement::Empty => 'L',
+            GridElement::Occupied => '#',
+        }
+    }
+
+    fn ansi_colour(&self) -> &'static str {
+        match self {
+            GridElement::Ground => "\x1b[90m",
+            GridElement::Empty => "\x1b[32m",
+            GridElement::Occupied => "\x1b[31m",
+        }
+    }
+}
+
+impl Map {
+    pub fn to_ansi(&self) -> String {
+        let mut frame = String::new();
+        for line in &self.map[1..self.map.len().saturating_sub(1)] {
+            for elem in &line[1..line.len() - 1] {
+                frame.push_str(elem.ansi_colour());
+                frame.push(elem.symbol());
+            }
+            frame.push_str("\x1b[0m\n");
+        }
+        frame
+    }
+}
+
+// which cells count as neighbours of a cell
+pub trait Neighbourhood {
+    fn neighbours(&self, map: &Map, y: usize, x: usize) -> Vec<(usize, usize)>;
+}
+
+pub struct Adjacent;
+
+impl Neighbourhood for Adjacent {
+    fn neighbours(&self, map: &Map, y: usize, x: usize) -> Vec<(usize, usize)> {
+        DIRECTIONS
+            .iter()
+            .map(|(dy, dx)| (y as isize + dy, x as isize + dx))
+            .filter(|(y, x)| *y >= 0 && *x >= 0)
+            .map(|(y, x)| (y as usize, x as usize))
+            .filter(|(y, x)| map.get(*y, *x).is_some())
+            .collect()
+    }
+}
+
+pub struct LineOfSight;
+
+impl Neighbourhood for LineOfSight {
+    fn neighbours(&self, map: &Map, y: usize, x: usize) -> Vec<(usize, usize)> {
+        map.visible_seats(y, x)
+    }
+}
+
+impl<F: Fn(&Map, usize, usize) -> Vec<(usize, usize)>> Neighbourhood for F {
+    fn neighbours(&self, map: &Map, y: usize, x: usize) -> Vec<(usize, usize)> {
+        self(map, y, x)
+    }
+}
+
+// the next state of a cell given how many of its neighbours are occupied
+pub trait Rule {
+    fn next(&self, cell: &GridElement, occupied_neighbours: usize) -> GridElement;
+}
+
+// seats are taken when no neighbour is occupied and left when at least
+// `tolerance` neighbours are
+pub struct SeatingRule {
+    pub tolerance: usize,
+}
+
+impl Rule for SeatingRule {
+    fn next(&self, cell: &GridElement, occupied_neighbours: usize) -> GridElement {
+        match cell {
+            GridElement::Empty if occupied_neighbours == 0 => GridElement::Occupied,
+            GridElement::Occupied if occupied_neighbours >= self.tolerance => GridElement::Empty,
+            _ => cell.clone(),
+        }
+    }
+}
+
+impl<F: Fn(&GridElement, usize) -> GridElement> Rule for F {
+    fn next(&self, cell: &GridElement, occupied_neighbours: usize) -> GridElement {
+        self(cell, occupied_neighbours)
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum End {
+    FixedPoint,
+    Cycle { start: usize, length: usize }, // generation first seen, and period
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Outcome {
+    pub map: Map,
+    pub generations: usize, // steps taken until the map stopped changing or started repeating
+    pub end: End,
+}
+
+// the neighbours of every cell are computed once since seats never move
+pub struct Automaton<R: Rule> {
+    neighbours: Vec<Vec<Vec<(usize, usize)>>>,
+    rule: R,
+}
+
+impl<R: Rule> Automaton<R> {
+    pub fn new<N: Neighbourhood>(map: &Map, neighbourhood: &N, rule: R) -> Automaton<R> {
+        Automaton {
+            neighbours: map
+                .map
+                .iter()
+                .enumerate()
+                .map(|(y, line)| {
+                    (0..line.len())
+                        .map(|x| match line[x] {
+                            GridElement::Ground => Vec::new(),
+                            _ => neighbourhood.neighbours(map, y, x),
+                        })
+                        .collect()
+                })
+                .collect(),
+            rule,
+        }
+    }
+
+    pub fn step(&self, map: &Map) -> Map {
+        Map {
+            map: map
+                .map
+                .iter()
+                .enumerate()
+                .map(|(y, line)| {
+                    line.iter()
+                        .enumerate()
+                        .map(|(x, elem)| {
+                            let occupied = self.neighbours[y][x]
+                                .iter()
+                                .filter(|(y, x)| map.map[*y][*x] == GridElement::Occupied)
+                                .count();
+                            self.rule.next(elem, occupied)
+                        })
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    pub fn run(&self, map: &Map) -> Outcome {
+        let mut seen = HashMap::new();
+        let mut map = map.clone();
+        let mut generation = 0;
+        loop {
+            let next_map = self.step(&map);
+            if next_map == map {
+                return Outcome {
+                    map,
+                    generations: generation,
+                    end: End::FixedPoint,
+                };
+            }
+            seen.insert(map, generation);
+            generation += 1;
+            if let Some(start) = seen.get(&next_map) {
+                return Outcome {
+                    generations: generation,
+                    end: End::Cycle {
+                        start: *start,
+                        length: generation - start,
+                    },
+                    map: next_map,
+                };
+            }
+            map = next_map;
+        }
+    }
+}
+
 pub fn parse_input(input: &str) -> Map {
     let mut map = input
         .lines()
@@ -152,28 +354,159 @@ pub fn parse_input(input: &str) -> Map {
     Map { map }
 }
 
-pub fn solve_part1(input: &str) -> usize {
-    let mut map = parse_input(input);
-    loop {
-        let next_map = map.step_part1();
-        if next_map == map {
-            return map.count_occupied_seats();
+// the same rules as `Automaton` over flat double buffers: a generation only
+// re-evaluates the cells next to a change and doesn't allocate
+pub struct Simulation<R: Rule> {
+    width: usize,
+    cells: Vec<GridElement>,
+    next_cells: Vec<GridElement>,
+    neighbours: Vec<usize>, // neighbours of cell i are neighbours[offsets[i]..offsets[i + 1]]
+    offsets: Vec<usize>,
+    watchers: Vec<usize>, // cells having cell i as neighbour, indexed by watcher_offsets
+    watcher_offsets: Vec<usize>,
+    dirty: Vec<bool>,
+    next_dirty: Vec<bool>,
+    generations: usize,
+    rule: R,
+}
+
+impl<R: Rule> Simulation<R> {
+    pub fn new<N: Neighbourhood>(map: &Map, neighbourhood: &N, rule: R) -> Simulation<R> {
+        let width = map.width();
+        let cells = map.map.concat();
+
+        let mut neighbours = Vec::new();
+        let mut offsets = vec![0];
+        let mut watched_by = vec![Vec::new(); cells.len()];
+        for (i, cell) in cells.iter().enumerate() {
+            if *cell != GridElement::Ground {
+                for (y, x) in neighbourhood.neighbours(map, i / width, i % width) {
+                    neighbours.push(y * width + x);
+                    watched_by[y * width + x].push(i);
+                }
+            }
+            offsets.push(neighbours.len());
+        }
+        let mut watcher_offsets = vec![0];
+        for watchers in &watched_by {
+            watcher_offsets.push(watcher_offsets.last().unwrap() + watchers.len());
+        }
+
+        Simulation {
+            width,
+            next_cells: cells.clone(),
+            dirty: vec![true; cells.len()],
+            next_dirty: vec![false; cells.len()],
+            cells,
+            neighbours,
+            offsets,
+            watchers: watched_by.concat(),
+            watcher_offsets,
+            generations: 0,
+            rule,
         }
-        map = next_map;
     }
-}
 
-pub fn solve_part2(input: &str) -> usize {
-    let mut map = parse_input(input);
-    loop {
-        let next_map = map.step_part2();
-        if next_map == map {
-            return map.count_occupied_seats();
+    // whether any cell changed
+    pub fn step(&mut self) -> bool {
+        let mut changed = false;
+        for i in 0..self.cells.len() {
+            if !self.dirty[i] {
+                // unchanged by the previous step, so `next_cells` already holds it
+                continue;
+            }
+            self.dirty[i] = false;
+
+            let cells = &self.cells;
+            let occupied = self.neighbours[self.offsets[i]..self.offsets[i + 1]]
+                .iter()
+                .filter(|neighbour| cells[**neighbour] == GridElement::Occupied)
+                .count();
+            let next = self.rule.next(&self.cells[i], occupied);
+            if next != self.cells[i] {
+                changed = true;
+                self.next_dirty[i] = true;
+                for watcher in &self.watchers[self.watcher_offsets[i]..self.watcher_offsets[i + 1]]
+                {
+                    self.next_dirty[*watcher] = true;
+                }
+            }
+            self.next_cells[i] = next;
+        }
+
+        mem::swap(&mut self.cells, &mut self.next_cells);
+        mem::swap(&mut self.dirty, &mut self.next_dirty);
+        if changed {
+            self.generations += 1;
+        }
+        changed
+    }
+
+    // steps until a fixed point, giving up after `max_generations` changes
+    pub fn run(&mut self, max_generations: usize) -> Option<usize> {
+        while self.step() {
+            if self.generations > max_generations {
+                return None;
+            }
+        }
+        Some(self.generations)
+    }
+
+    pub fn generations(&self) -> usize {
+        self.generations
+    }
+
+    pub fn count_occupied_seats(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|elem| **elem == GridElement::Occupied)
+            .count()
+    }
+
+    pub fn count_empty_seats(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|elem| **elem == GridElement::Empty)
+            .count()
+    }
+
+    pub fn to_map(&self) -> Map {
+        Map {
+            map: self
+                .cells
+                .chunks(self.width.max(1))
+                .map(|line| line.to_vec())
+                .collect(),
         }
-        map = next_map;
     }
 }
 
+pub fn solve_part1(input: &str) -> usize {
+    let map = parse_input(input);
+    let mut simulation = Simulation::new(&map, &Adjacent, SeatingRule { tolerance: 4 });
+    simulation.run(usize::MAX).unwrap();
+    simulation.count_occupied_seats()
+}
+
+pub fn solve_part2(input: &str) -> usize {
+    let map = parse_input(input);
+    let mut simulation = Simulation::new(&map, &LineOfSight, SeatingRule { tolerance: 5 });
+    simulation.run(usize::MAX).unwrap();
+    simulation.count_occupied_seats()
+}
+
+pub fn solve_part1_automaton(input: &str) -> usize {
+    let map = parse_input(input);
+    let automaton = Automaton::new(&map, &Adjacent, SeatingRule { tolerance: 4 });
+    automaton.run(&map).map.count_occupied_seats()
+}
+
+pub fn solve_part2_automaton(input: &str) -> usize {
+    let map = parse_input(input);
+    let automaton = Automaton::new(&map, &LineOfSight, SeatingRule { tolerance: 5 });
+    automaton.run(&map).map.count_occupied_seats()
+}
+
 pub fn part1() {
     let mut file = File::open("input/2020/day11.txt").unwrap();
     let mut input = String::new();
@@ -188,6 +521,82 @@ pub fn part2() {
     println!("{}", solve_part2(&input));
 }
 
+#[derive(PartialEq, Debug, Clone)]
+pub struct GenerationStats {
+    pub generation: usize,
+    pub occupied: usize,
+    pub empty: usize,
+}
+
+// writes every generation until the layout stops changing, then a summary;
+// `ansi` redraws the frames in place with colours, waiting `delay` between them
+pub fn write_frames<R: Rule, W: Write>(
+    simulation: &mut Simulation<R>,
+    ansi: bool,
+    delay: Duration,
+    out: &mut W,
+) -> io::Result<Vec<GenerationStats>> {
+    let mut stats = Vec::new();
+    loop {
+        let generation = GenerationStats {
+            generation: simulation.generations(),
+            occupied: simulation.count_occupied_seats(),
+            empty: simulation.count_empty_seats(),
+        };
+        let map = simulation.to_map();
+        if ansi {
+            write!(out, "\x1b[2J\x1b[H{}", map.to_ansi())?;
+        } else {
+            write!(out, "{}", map)?;
+        }
+        writeln!(
+            out,
+            "generation {}: {} occupied, {} empty\n",
+            generation.generation, generation.occupied, generation.empty
+        )?;
+        out.flush()?;
+        stats.push(generation);
+
+        if !simulation.step() {
+            break;
+        }
+        thread::sleep(delay);
+    }
+
+    writeln!(out, "generation,occupied,empty")?;
+    for generation in &stats {
+        writeln!(
+            out,
+            "{},{},{}",
+            generation.generation, generation.occupied, generation.empty
+        )?;
+    }
+    Ok(stats)
+}
+
+pub fn visualize(args: &[&str]) {
+    let mut file = File::open("input/2020/day11.txt").unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+    let map = parse_input(&input);
+    let ansi = args.contains(&"--ansi");
+    let delay = if ansi {
+        Duration::from_millis(100)
+    } else {
+        Duration::from_millis(0)
+    };
+    let stdout = io::stdout();
+    let mut out = stdout.lock();
+
+    if args.contains(&"part2") {
+        let mut simulation = Simulation::new(&map, &LineOfSight, SeatingRule { tolerance: 5 });
+        write_frames(&mut simulation, ansi, delay, &mut out).unwrap();
+    } else {
+        let mut simulation = Simulation::new(&map, &Adjacent, SeatingRule { tolerance: 4 });
+        write_frames(&mut simulation, ansi, delay, &mut out).unwrap();
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -270,4 +679,142 @@ mod test {
             }
         )
     }
+
+    #[test]
+    fn test_map_step_part2_example() {
+        assert_eq!(parse_input("#.##.##.##\n#######.##\n#.#.#..#..\n####.##.##\n#.##.##.##\n#.#####.##\n..#.#.....\n##########\n#.######.#\n#.#####.##").step_part2(), parse_input("#.LL.LL.L#\n#LLLLLL.LL\nL.L.L..L..\nLLLL.LL.LL\nL.LL.LL.LL\nL.LLLLL.LL\n..L.L.....\nLLLLLLLLL#\n#.LLLLLL.L\n#.LLLLL.L#"));
+        assert_eq!(parse_input("#.LL.LL.L#\n#LLLLLL.LL\nL.L.L..L..\nLLLL.LL.LL\nL.LL.LL.LL\nL.LLLLL.LL\n..L.L.....\nLLLLLLLLL#\n#.LLLLLL.L\n#.LLLLL.L#").step_part2(), parse_input("#.L#.##.L#\n#L#####.LL\nL.#.#..#..\n##L#.##.##\n#.##.#L.##\n#.#####.#L\n..#.#.....\nLLL####LL#\n#.L#####.L\n#.L####.L#"));
+    }
+
+    #[test]
+    fn test_solve_part2_example() {
+        assert_eq!(solve_part2("L.LL.LL.LL\nLLLLLLL.LL\nL.L.L..L..\nLLLL.LL.LL\nL.LL.LL.LL\nL.LLLLL.LL\n..L.L.....\nLLLLLLLLLL\nL.LLLLLL.L\nL.LLLLL.LL"), 26);
+    }
+
+    #[test]
+    fn test_map_visible_seats() {
+        // NOTE: the map is offset by 1 in X and Y as we add a GridElement::Ground around the map
+        let map = parse_input(".##.##.\n#.#.#.#\n##...##\n...L...\n##...##\n#.#.#.#\n.##.##.");
+        assert_eq!(map.visible_seats(4, 4), vec![]);
+        assert!(map.is_seat_free_to_take_part2(4, 4));
+        let map = parse_input(".......#.\n...#.....\n.#.......\n.........\n..#L....#\n....#....\n.........\n#........\n...#.....");
+        assert_eq!(map.visible_seats(5, 4).len(), 8);
+        assert!(!map.is_seat_free_to_take_part2(5, 4));
+        let map = parse_input(".............\n.L.L.#.#.#.#.\n.............");
+        assert_eq!(map.visible_seats(2, 2), vec![(2, 4)]);
+        assert!(map.is_seat_free_to_take_part2(2, 2));
+    }
+
+    #[test]
+    fn test_solve_part1_example() {
+        assert_eq!(solve_part1("L.LL.LL.LL\nLLLLLLL.LL\nL.L.L..L..\nLLLL.LL.LL\nL.LL.LL.LL\nL.LLLLL.LL\n..L.L.....\nLLLLLLLLLL\nL.LLLLLL.L\nL.LLLLL.LL"), 37);
+    }
+
+    #[test]
+    fn test_automaton_fixed_point() {
+        let map = parse_input("L.LL.LL.LL\nLLLLLLL.LL\nL.L.L..L..\nLLLL.LL.LL\nL.LL.LL.LL\nL.LLLLL.LL\n..L.L.....\nLLLLLLLLLL\nL.LLLLLL.L\nL.LLLLL.LL");
+        let outcome = Automaton::new(&map, &Adjacent, SeatingRule { tolerance: 4 }).run(&map);
+        assert_eq!(outcome.end, End::FixedPoint);
+        assert_eq!(outcome.generations, 5);
+        assert_eq!(outcome.map.count_occupied_seats(), 37);
+    }
+
+    #[test]
+    fn test_automaton_custom_cycle() {
+        // every seat flips each generation
+        let map = parse_input("L#\n#L");
+        let flip = |cell: &GridElement, _: usize| match cell {
+            GridElement::Empty => GridElement::Occupied,
+            GridElement::Occupied => GridElement::Empty,
+            GridElement::Ground => GridElement::Ground,
+        };
+        let no_neighbours = |_: &Map, _: usize, _: usize| Vec::new();
+        let outcome = Automaton::new(&map, &no_neighbours, flip).run(&map);
+        assert_eq!(
+            outcome.end,
+            End::Cycle {
+                start: 0,
+                length: 2
+            }
+        );
+        assert_eq!(outcome.generations, 2);
+        assert_eq!(outcome.map, map);
+    }
+
+    #[test]
+    fn test_simulation_matches_automaton() {
+        let map = parse_input("L.LL.LL.LL\nLLLLLLL.LL\nL.L.L..L..\nLLLL.LL.LL\nL.LL.LL.LL\nL.LLLLL.LL\n..L.L.....\nLLLLLLLLLL\nL.LLLLLL.L\nL.LLLLL.LL");
+        let automaton = Automaton::new(&map, &LineOfSight, SeatingRule { tolerance: 5 });
+        let mut simulation = Simulation::new(&map, &LineOfSight, SeatingRule { tolerance: 5 });
+        let mut expected = map.clone();
+        for _ in 0..3 {
+            expected = automaton.step(&expected);
+            assert!(simulation.step());
+            assert_eq!(simulation.to_map(), expected);
+        }
+        assert_eq!(simulation.run(100), Some(6));
+        assert_eq!(simulation.to_map(), automaton.run(&map).map);
+        assert_eq!(simulation.count_occupied_seats(), 26);
+        assert!(!simulation.step());
+        assert_eq!(simulation.generations(), 6);
+    }
+
+    #[test]
+    fn test_simulation_gives_up_on_cycles() {
+        let flip = |cell: &GridElement, _: usize| match cell {
+            GridElement::Empty => GridElement::Occupied,
+            GridElement::Occupied => GridElement::Empty,
+            GridElement::Ground => GridElement::Ground,
+        };
+        let mut simulation = Simulation::new(&parse_input("L#\n#L"), &Adjacent, flip);
+        assert_eq!(simulation.run(10), None);
+    }
+
+    #[test]
+    fn test_map_display() {
+        let map = parse_input("L.#\n#LL");
+        assert_eq!(map.to_string(), "L.#\n#LL\n");
+        assert_eq!(
+            map.to_ansi(),
+            "\x1b[32mL\x1b[90m.\x1b[31m#\x1b[0m\n\x1b[31m#\x1b[32mL\x1b[32mL\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_write_frames() {
+        let map = parse_input("L.L\nLLL");
+        let mut simulation = Simulation::new(&map, &Adjacent, SeatingRule { tolerance: 4 });
+        let mut out = Vec::new();
+        let stats =
+            write_frames(&mut simulation, false, Duration::from_millis(0), &mut out).unwrap();
+        assert_eq!(
+            stats,
+            vec![
+                GenerationStats {
+                    generation: 0,
+                    occupied: 0,
+                    empty: 5
+                },
+                GenerationStats {
+                    generation: 1,
+                    occupied: 5,
+                    empty: 0
+                },
+                GenerationStats {
+                    generation: 2,
+                    occupied: 4,
+                    empty: 1
+                },
+            ]
+        );
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                "L.L\nLLL\ngeneration 0: 0 occupied, 5 empty\n\n",
+                "#.#\n###\ngeneration 1: 5 occupied, 0 empty\n\n",
+                "#.#\n#L#\ngeneration 2: 4 occupied, 1 empty\n\n",
+                "generation,occupied,empty\n0,0,5\n1,5,0\n2,4,1\n"
+            )
+        );
+    }
 }
@@ -1,6 +1,7 @@
-use regex::Regex;
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
+use std::marker::PhantomData;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Action {
@@ -8,208 +9,560 @@ pub enum Action {
     MoveSouth,
     MoveEast,
     MoveWest,
+    MoveNorthEast,
+    MoveNorthWest,
+    MoveSouthEast,
+    MoveSouthWest,
     TurnRight,
     TurnLeft,
     MoveForward,
+    GoToWaypoint, // move onto the waypoint, which is used up (part 2 only)
 }
 
+#[derive(PartialEq, Debug, Clone)]
+pub enum NavigationError {
+    InvalidTurn(i32), // not a multiple of 90 degrees
+    NoWaypoint,       // going to a waypoint while navigating by heading
+    Overflow,         // the ship or its vector would leave the i32 range
+}
+
+impl fmt::Display for NavigationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NavigationError::InvalidTurn(degrees) => {
+                write!(f, "cannot turn {} degrees, only right angles", degrees)
+            }
+            NavigationError::NoWaypoint => write!(f, "there is no waypoint to go to"),
+            NavigationError::Overflow => write!(f, "the ship sailed off the map"),
+        }
+    }
+}
+
+// number of clockwise quarter turns equivalent to turning `degrees` to the right
+fn quarter_turns(degrees: i32) -> Result<i32, NavigationError> {
+    if degrees % 90 != 0 {
+        return Err(NavigationError::InvalidTurn(degrees));
+    }
+    Ok((degrees / 90).rem_euclid(4))
+}
+
+// `value + step * times`, failing rather than overflowing
+fn offset(value: i32, step: i32, times: i32) -> Result<i32, NavigationError> {
+    step.checked_mul(times)
+        .and_then(|step| value.checked_add(step))
+        .ok_or(NavigationError::Overflow)
+}
+
+// what the cardinal moves and going to the waypoint act on, the rest of the
+// navigation is shared
+pub trait Navigation {
+    fn translate(ship: &mut Ship<Self>, dy: i32, dx: i32) -> Result<(), NavigationError>
+    where
+        Self: Sized;
+
+    fn go_to_waypoint(ship: &mut Ship<Self>) -> Result<(), NavigationError>
+    where
+        Self: Sized;
+}
+
+// part 1: cardinal moves push the ship, the vector is its heading
+#[derive(Debug)]
+pub struct Heading;
+
+impl Navigation for Heading {
+    fn translate(ship: &mut Ship<Heading>, dy: i32, dx: i32) -> Result<(), NavigationError> {
+        let position_y = offset(ship.position_y, dy, 1)?;
+        let position_x = offset(ship.position_x, dx, 1)?;
+        ship.position_y = position_y;
+        ship.position_x = position_x;
+        Ok(())
+    }
+
+    fn go_to_waypoint(_: &mut Ship<Heading>) -> Result<(), NavigationError> {
+        Err(NavigationError::NoWaypoint)
+    }
+}
+
+// part 2: cardinal moves push the waypoint, relative to the ship
 #[derive(Debug)]
-pub struct BoatPart1 {
-    angle: i32,
-    orientation_y: i32,
-    orientation_x: i32,
+pub struct Waypoint;
+
+impl Navigation for Waypoint {
+    fn translate(ship: &mut Ship<Waypoint>, dy: i32, dx: i32) -> Result<(), NavigationError> {
+        let vector_y = offset(ship.vector_y, dy, 1)?;
+        let vector_x = offset(ship.vector_x, dx, 1)?;
+        ship.vector_y = vector_y;
+        ship.vector_x = vector_x;
+        Ok(())
+    }
+
+    // the ship lands on the waypoint, leaving it to be set again from there
+    fn go_to_waypoint(ship: &mut Ship<Waypoint>) -> Result<(), NavigationError> {
+        ship.move_forward(1)?;
+        ship.vector_y = 0;
+        ship.vector_x = 0;
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub struct Ship<N: Navigation> {
+    navigation: PhantomData<N>,
     position_y: i32,
     position_x: i32,
+    vector_y: i32, // heading or waypoint depending on the navigation
+    vector_x: i32,
 }
 
-impl BoatPart1 {
+pub type BoatPart1 = Ship<Heading>;
+pub type BoatPart2 = Ship<Waypoint>;
+
+impl Ship<Heading> {
     pub fn new() -> BoatPart1 {
-        BoatPart1 {
-            angle: 90,
-            orientation_y: 0,
-            orientation_x: 1, // the boat starts facing east
+        Ship::with_vector(0, 1) // the boat starts facing east
+    }
+}
+
+impl Default for Ship<Heading> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ship<Waypoint> {
+    pub fn new() -> BoatPart2 {
+        Ship::with_vector(1, 10)
+    }
+}
+
+impl Default for Ship<Waypoint> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Navigation> Ship<N> {
+    pub fn with_vector(vector_y: i32, vector_x: i32) -> Ship<N> {
+        Ship {
+            navigation: PhantomData,
             position_y: 0,
             position_x: 0,
+            vector_y,
+            vector_x,
         }
     }
 
-    pub fn get_dist_from_start(&self) -> i32 {
-        self.position_x.abs() + self.position_y.abs()
+    pub fn position(&self) -> (i32, i32) {
+        (self.position_y, self.position_x)
+    }
+
+    pub fn vector(&self) -> (i32, i32) {
+        (self.vector_y, self.vector_x)
+    }
+
+    // i64 as both coordinates can be close to the i32 bounds
+    pub fn get_dist_from_start(&self) -> i64 {
+        (self.position_x as i64).abs() + (self.position_y as i64).abs()
     }
 
-    pub fn do_action(&mut self, action: Action, value: i32) {
+    pub fn do_action(&mut self, action: Action, value: i32) -> Result<(), NavigationError> {
+        let back = || value.checked_neg().ok_or(NavigationError::Overflow);
         match action {
-            Action::MoveNorth => self.move_north(value),
-            Action::MoveSouth => self.move_south(value),
-            Action::MoveEast => self.move_east(value),
-            Action::MoveWest => self.move_west(value),
-            Action::TurnLeft => self.turn_left(value),
-            Action::TurnRight => self.turn_right(value),
+            Action::MoveNorth => N::translate(self, value, 0),
+            Action::MoveSouth => N::translate(self, back()?, 0),
+            Action::MoveEast => N::translate(self, 0, value),
+            Action::MoveWest => N::translate(self, 0, back()?),
+            Action::MoveNorthEast => N::translate(self, value, value),
+            Action::MoveNorthWest => N::translate(self, value, back()?),
+            Action::MoveSouthEast => N::translate(self, back()?, value),
+            Action::MoveSouthWest => N::translate(self, back()?, back()?),
+            Action::TurnLeft => self.rotate((4 - quarter_turns(value)?) % 4),
+            Action::TurnRight => self.rotate(quarter_turns(value)?),
             Action::MoveForward => self.move_forward(value),
+            Action::GoToWaypoint => N::go_to_waypoint(self),
         }
     }
 
-    fn move_south(&mut self, value: i32) {
-        self.position_y -= value
+    // clockwise, exact on integers
+    fn rotate(&mut self, quarter_turns: i32) -> Result<(), NavigationError> {
+        let (mut y, mut x) = (self.vector_y, self.vector_x);
+        for _ in 0..quarter_turns {
+            let turned_y = x.checked_neg().ok_or(NavigationError::Overflow)?;
+            x = y;
+            y = turned_y;
+        }
+        self.vector_y = y;
+        self.vector_x = x;
+        Ok(())
     }
 
-    fn move_north(&mut self, value: i32) {
-        self.position_y += value
+    fn move_forward(&mut self, value: i32) -> Result<(), NavigationError> {
+        let position_y = offset(self.position_y, self.vector_y, value)?;
+        let position_x = offset(self.position_x, self.vector_x, value)?;
+        self.position_y = position_y;
+        self.position_x = position_x;
+        Ok(())
     }
+}
 
-    fn move_east(&mut self, value: i32) {
-        self.position_x += value
+pub fn navigate<N: Navigation>(
+    mut ship: Ship<N>,
+    input: &[(Action, i32)],
+) -> Result<Ship<N>, NavigationError> {
+    for (action, value) in input {
+        ship.do_action(*action, *value)?;
     }
+    Ok(ship)
+}
 
-    fn move_west(&mut self, value: i32) {
-        self.position_x -= value
-    }
+// ship state after an instruction, (y, x) with y pointing north
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct RoutePoint {
+    pub position: (i32, i32),
+    pub vector: (i32, i32),
+}
 
-    fn turn_left(&mut self, value: i32) {
-        self.angle -= value;
-        self.orientation_y = (self.angle as f64).to_radians().cos() as i32;
-        self.orientation_x = (self.angle as f64).to_radians().sin() as i32;
-    }
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct BoundingBox {
+    pub min_y: i32,
+    pub min_x: i32,
+    pub max_y: i32,
+    pub max_x: i32,
+}
 
-    fn turn_right(&mut self, value: i32) {
-        self.angle += value;
-        self.orientation_y = (self.angle as f64).to_radians().cos() as i32;
-        self.orientation_x = (self.angle as f64).to_radians().sin() as i32;
+// sizes and distances are i64, positions can span the whole i32 range
+impl BoundingBox {
+    pub fn height(&self) -> i64 {
+        self.max_y as i64 - self.min_y as i64
     }
 
-    fn move_forward(&mut self, value: i32) {
-        self.position_x += self.orientation_x * value;
-        self.position_y += self.orientation_y * value;
+    pub fn width(&self) -> i64 {
+        self.max_x as i64 - self.min_x as i64
     }
 }
 
-struct BoatPart2 {
-    position_y: i32,
-    position_x: i32,
-    waypoint_y: i32,
-    waypoint_x: i32,
+fn manhattan((y, x): (i32, i32), (to_y, to_x): (i32, i32)) -> i64 {
+    (to_y as i64 - y as i64).abs() + (to_x as i64 - x as i64).abs()
 }
 
-impl BoatPart2 {
-    pub fn new() -> BoatPart2 {
-        BoatPart2 {
-            position_y: 0,
-            position_x: 0,
-            waypoint_y: 1,
-            waypoint_x: 10,
-        }
+#[derive(Debug)]
+pub struct Route {
+    points: Vec<RoutePoint>, // starting state first, then one per instruction
+}
+
+impl Route {
+    pub fn points(&self) -> &[RoutePoint] {
+        &self.points
     }
 
-    pub fn get_dist_from_start(&self) -> i32 {
-        self.position_x.abs() + self.position_y.abs()
+    pub fn max_distance(&self) -> i64 {
+        self.points
+            .iter()
+            .map(|point| manhattan((0, 0), point.position))
+            .max()
+            .unwrap()
     }
 
-    pub fn do_action(&mut self, action: Action, value: i32) {
-        match action {
-            Action::MoveNorth => self.move_north(value),
-            Action::MoveSouth => self.move_south(value),
-            Action::MoveEast => self.move_east(value),
-            Action::MoveWest => self.move_west(value),
-            Action::TurnLeft => self.turn_left(value),
-            Action::TurnRight => self.turn_right(value),
-            Action::MoveForward => self.move_forward(value),
-        }
+    pub fn bounding_box(&self) -> BoundingBox {
+        let (y, x) = self.points[0].position;
+        self.points.iter().fold(
+            BoundingBox {
+                min_y: y,
+                min_x: x,
+                max_y: y,
+                max_x: x,
+            },
+            |bounds, point| BoundingBox {
+                min_y: bounds.min_y.min(point.position.0),
+                min_x: bounds.min_x.min(point.position.1),
+                max_y: bounds.max_y.max(point.position.0),
+                max_x: bounds.max_x.max(point.position.1),
+            },
+        )
     }
 
-    fn move_south(&mut self, value: i32) {
-        self.waypoint_y -= value
+    // sum of the manhattan distances between consecutive positions
+    pub fn distance_travelled(&self) -> i64 {
+        self.points
+            .windows(2)
+            .map(|pair| manhattan(pair[0].position, pair[1].position))
+            .sum()
     }
 
-    fn move_north(&mut self, value: i32) {
-        self.waypoint_y += value
+    // north is up, so y is flipped in svg coordinates
+    pub fn to_svg(&self) -> String {
+        let bounds = self.bounding_box();
+        let pad = bounds.height().max(bounds.width()) / 20 + 1;
+        let (start_y, start_x) = self.points[0].position;
+        let (end_y, end_x) = self.points[self.points.len() - 1].position;
+        let polyline = self
+            .points
+            .iter()
+            .map(|point| format!("{},{}", point.position.1, -(point.position.0 as i64)))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+            bounds.min_x as i64 - pad,
+            -(bounds.max_y as i64) - pad,
+            bounds.width() + 2 * pad,
+            bounds.height() + 2 * pad
+        );
+        svg += &format!(
+            "    <polyline points=\"{}\" fill=\"none\" stroke=\"black\" stroke-width=\"1\" vector-effect=\"non-scaling-stroke\"/>\n",
+            polyline
+        );
+        svg += &format!(
+            "    <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"green\"/>\n",
+            start_x,
+            -(start_y as i64),
+            pad as f64 / 2.0
+        );
+        svg += &format!(
+            "    <circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"red\"/>\n",
+            end_x,
+            -(end_y as i64),
+            pad as f64 / 2.0
+        );
+        svg += "</svg>\n";
+        svg
     }
+}
 
-    fn move_east(&mut self, value: i32) {
-        self.waypoint_x += value
+pub fn trace<N: Navigation>(
+    mut ship: Ship<N>,
+    input: &[(Action, i32)],
+) -> Result<Route, NavigationError> {
+    let mut points = vec![RoutePoint {
+        position: ship.position(),
+        vector: ship.vector(),
+    }];
+    for (action, value) in input {
+        ship.do_action(*action, *value)?;
+        points.push(RoutePoint {
+            position: ship.position(),
+            vector: ship.vector(),
+        });
     }
+    Ok(Route { points })
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum ParseError {
+    UnknownCommand { line: usize, command: String },
+    InvalidValue { line: usize, command: String },
+    InvalidRepeatCount { line: usize, count: String },
+    MissingBrace { line: usize },
+    UnclosedRepeat { line: usize },
+    UnexpectedBrace { line: usize },
+    TooLarge { line: usize }, // the repeat block expands past `MAX_ACTIONS`
+}
 
-    fn move_west(&mut self, value: i32) {
-        self.waypoint_x -= value
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::UnknownCommand { line, command } => {
+                write!(f, "line {}: unknown command `{}`", line, command)
+            }
+            ParseError::InvalidValue { line, command } => {
+                write!(f, "line {}: invalid value in `{}`", line, command)
+            }
+            ParseError::InvalidRepeatCount { line, count } => {
+                write!(f, "line {}: invalid repeat count `{}`", line, count)
+            }
+            ParseError::MissingBrace { line } => {
+                write!(f, "line {}: expected `{{` after the repeat count", line)
+            }
+            ParseError::UnclosedRepeat { line } => {
+                write!(f, "line {}: repeat block is never closed", line)
+            }
+            ParseError::UnexpectedBrace { line } => {
+                write!(f, "line {}: `}}` without a matching repeat", line)
+            }
+            ParseError::TooLarge { line } => write!(
+                f,
+                "line {}: repeat block expands to more than {} instructions",
+                line, MAX_ACTIONS
+            ),
+        }
     }
+}
 
-    fn turn_left(&mut self, value: i32) {
-        let cos = (value as f64).to_radians().cos() as i32;
-        let sin = (value as f64).to_radians().sin() as i32;
-        let new_x = self.waypoint_x * cos - self.waypoint_y * sin;
-        let new_y = self.waypoint_x * sin + self.waypoint_y * cos;
-        self.waypoint_x = new_x;
-        self.waypoint_y = new_y;
+// upper bound on the number of instructions once repeat blocks are expanded
+pub const MAX_ACTIONS: usize = 1_000_000;
+
+fn parse_command(line: usize, command: &str) -> Result<(Action, i32), ParseError> {
+    let split = command
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(command.len());
+    let (name, value) = command.split_at(split);
+    let action = match name {
+        "N" => Action::MoveNorth,
+        "E" => Action::MoveEast,
+        "S" => Action::MoveSouth,
+        "W" => Action::MoveWest,
+        "NE" => Action::MoveNorthEast,
+        "NW" => Action::MoveNorthWest,
+        "SE" => Action::MoveSouthEast,
+        "SW" => Action::MoveSouthWest,
+        "L" => Action::TurnLeft,
+        "R" => Action::TurnRight,
+        "F" => Action::MoveForward,
+        "G" => Action::GoToWaypoint,
+        _ => {
+            return Err(ParseError::UnknownCommand {
+                line,
+                command: command.to_owned(),
+            })
+        }
+    };
+    let invalid_value = || ParseError::InvalidValue {
+        line,
+        command: command.to_owned(),
+    };
+    if action == Action::GoToWaypoint {
+        return if value.is_empty() {
+            Ok((action, 1))
+        } else {
+            Err(invalid_value())
+        };
+    }
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid_value());
     }
+    Ok((action, value.parse().map_err(|_| invalid_value())?))
+}
 
-    fn turn_right(&mut self, value: i32) {
-        let cos = (-value as f64).to_radians().cos() as i32;
-        let sin = (-value as f64).to_radians().sin() as i32;
-        let new_x = self.waypoint_x * cos - self.waypoint_y * sin;
-        let new_y = self.waypoint_x * sin + self.waypoint_y * cos;
-        self.waypoint_x = new_x;
-        self.waypoint_y = new_y;
+// one command per token, `# comment` to the end of the line, and
+// `repeat <count> { ... }` blocks, which may be nested and are expanded inline
+pub fn parse(source: &str) -> Result<Vec<(Action, i32)>, ParseError> {
+    let mut tokens = source.lines().enumerate().flat_map(|(line_number, line)| {
+        line.split('#')
+            .next()
+            .unwrap()
+            .split_whitespace()
+            .map(move |token| (line_number + 1, token))
+    });
+    let mut blocks = vec![(0, 1, Vec::new())]; // (line, count, actions) of each open block
+
+    while let Some((line, token)) = tokens.next() {
+        match token {
+            "repeat" => {
+                let word = tokens.next().map_or("", |(_, word)| word);
+                let count: usize = match word.parse() {
+                    Ok(count) if word.chars().all(|c| c.is_ascii_digit()) => count,
+                    _ => {
+                        return Err(ParseError::InvalidRepeatCount {
+                            line,
+                            count: word.to_owned(),
+                        })
+                    }
+                };
+                match tokens.next() {
+                    Some((_, "{")) => blocks.push((line, count, Vec::new())),
+                    _ => return Err(ParseError::MissingBrace { line }),
+                }
+            }
+            "}" => {
+                if blocks.len() == 1 {
+                    return Err(ParseError::UnexpectedBrace { line });
+                }
+                let (repeat_line, count, actions) = blocks.pop().unwrap();
+                let parent = &mut blocks.last_mut().unwrap().2;
+                let expanded_len = match actions.len().checked_mul(count) {
+                    Some(len) if len.saturating_add(parent.len()) <= MAX_ACTIONS => len,
+                    _ => return Err(ParseError::TooLarge { line: repeat_line }),
+                };
+                parent.extend(actions.iter().copied().cycle().take(expanded_len));
+            }
+            command => blocks
+                .last_mut()
+                .unwrap()
+                .2
+                .push(parse_command(line, command)?),
+        }
     }
 
-    fn move_forward(&mut self, value: i32) {
-        self.position_x += self.waypoint_x * value;
-        self.position_y += self.waypoint_y * value;
+    match blocks.pop() {
+        Some((_, _, actions)) if blocks.is_empty() => Ok(actions),
+        Some((line, _, _)) => Err(ParseError::UnclosedRepeat { line }),
+        None => unreachable!(),
     }
 }
 
 pub fn parse_input(input: &str) -> Vec<(Action, i32)> {
-    lazy_static! {
-        static ref RE: Regex = Regex::new(r"(?P<action>N|S|E|W|L|R|F)(?P<value>\d+)").unwrap();
-    }
-
-    input
-        .lines()
-        .map(|line| {
-            let captures = RE.captures(line).unwrap();
-            (
-                match &captures["action"] {
-                    "N" => Action::MoveNorth,
-                    "E" => Action::MoveEast,
-                    "S" => Action::MoveSouth,
-                    "W" => Action::MoveWest,
-                    "L" => Action::TurnLeft,
-                    "R" => Action::TurnRight,
-                    "F" => Action::MoveForward,
-                    _ => panic!(),
-                },
-                captures["value"].parse().unwrap(),
-            )
-        })
-        .collect()
-}
-
-pub fn solve_part1(input: &[(Action, i32)]) -> i32 {
-    let mut boat = BoatPart1::new();
-    input
-        .iter()
-        .for_each(|(action, value)| boat.do_action(*action, *value));
-    boat.get_dist_from_start()
+    parse(input).unwrap()
+}
+
+pub fn solve_part1(input: &[(Action, i32)]) -> i64 {
+    navigate(BoatPart1::new(), input)
+        .unwrap()
+        .get_dist_from_start()
 }
 
 pub fn part1() {
     let mut file = File::open("input/2020/day12.txt").unwrap();
     let mut input = String::new();
     file.read_to_string(&mut input).unwrap();
-    println!("{}", solve_part1(&parse_input(&input)));
+    match parse(&input) {
+        Ok(input) => match navigate(BoatPart1::new(), &input) {
+            Ok(ship) => println!("{}", ship.get_dist_from_start()),
+            Err(error) => eprintln!("{}", error),
+        },
+        Err(error) => eprintln!("{}", error),
+    }
 }
 
-pub fn solve_part2(input: &[(Action, i32)]) -> i32 {
-    let mut boat = BoatPart2::new();
-    input
-        .iter()
-        .for_each(|(action, value)| boat.do_action(*action, *value));
-    boat.get_dist_from_start()
+pub fn solve_part2(input: &[(Action, i32)]) -> i64 {
+    navigate(BoatPart2::new(), input)
+        .unwrap()
+        .get_dist_from_start()
 }
 
 pub fn part2() {
     let mut file = File::open("input/2020/day12.txt").unwrap();
     let mut input = String::new();
     file.read_to_string(&mut input).unwrap();
-    println!("{}", solve_part2(&parse_input(&input)));
+    match parse(&input) {
+        Ok(input) => match navigate(BoatPart2::new(), &input) {
+            Ok(ship) => println!("{}", ship.get_dist_from_start()),
+            Err(error) => eprintln!("{}", error),
+        },
+        Err(error) => eprintln!("{}", error),
+    }
+}
+
+pub fn report(args: &[&str]) {
+    let mut file = File::open("input/2020/day12.txt").unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+    let input = match parse(&input) {
+        Ok(input) => input,
+        Err(error) => {
+            eprintln!("{}", error);
+            return;
+        }
+    };
+    let route = if args.contains(&"part2") {
+        trace(BoatPart2::new(), &input)
+    } else {
+        trace(BoatPart1::new(), &input)
+    };
+    match route {
+        Ok(route) if args.contains(&"--svg") => print!("{}", route.to_svg()),
+        Ok(route) => {
+            let bounds = route.bounding_box();
+            println!("instructions: {}", route.points().len() - 1);
+            println!("max distance: {}", route.max_distance());
+            println!("distance travelled: {}", route.distance_travelled());
+            println!(
+                "bounding box: y {}..={}, x {}..={}",
+                bounds.min_y, bounds.max_y, bounds.min_x, bounds.max_x
+            );
+        }
+        Err(error) => eprintln!("{}", error),
+    }
 }
 
 #[cfg(test)]
@@ -233,36 +586,36 @@ mod test {
     #[test]
     fn test_part1_example_actions() {
         let mut boat = BoatPart1::new();
-        boat.do_action(Action::MoveForward, 10);
-        assert_eq!((boat.position_y, boat.position_x), (0, 10));
-        boat.do_action(Action::MoveNorth, 3);
-        assert_eq!((boat.position_y, boat.position_x), (3, 10));
-        boat.do_action(Action::MoveForward, 7);
-        assert_eq!((boat.position_y, boat.position_x), (3, 17));
-        boat.do_action(Action::TurnRight, 90);
-        assert_eq!((boat.orientation_y, boat.orientation_x), (-1, 0));
-        boat.do_action(Action::MoveForward, 11);
-        assert_eq!((boat.position_y, boat.position_x), (-8, 17));
+        boat.do_action(Action::MoveForward, 10).unwrap();
+        assert_eq!(boat.position(), (0, 10));
+        boat.do_action(Action::MoveNorth, 3).unwrap();
+        assert_eq!(boat.position(), (3, 10));
+        boat.do_action(Action::MoveForward, 7).unwrap();
+        assert_eq!(boat.position(), (3, 17));
+        boat.do_action(Action::TurnRight, 90).unwrap();
+        assert_eq!(boat.vector(), (-1, 0));
+        boat.do_action(Action::MoveForward, 11).unwrap();
+        assert_eq!(boat.position(), (-8, 17));
     }
 
     #[test]
     fn test_part2_example_actions() {
         let mut boat = BoatPart2::new();
-        boat.do_action(Action::MoveForward, 10);
-        assert_eq!((boat.position_y, boat.position_x), (10, 100));
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (1, 10));
-        boat.do_action(Action::MoveNorth, 3);
-        assert_eq!((boat.position_y, boat.position_x), (10, 100));
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (4, 10));
-        boat.do_action(Action::MoveForward, 7);
-        assert_eq!((boat.position_y, boat.position_x), (38, 170));
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (4, 10));
-        boat.do_action(Action::TurnRight, 90);
-        assert_eq!((boat.position_y, boat.position_x), (38, 170));
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (-10, 4));
-        boat.do_action(Action::MoveForward, 11);
-        assert_eq!((boat.position_y, boat.position_x), (-72, 214));
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (-10, 4));
+        boat.do_action(Action::MoveForward, 10).unwrap();
+        assert_eq!(boat.position(), (10, 100));
+        assert_eq!(boat.vector(), (1, 10));
+        boat.do_action(Action::MoveNorth, 3).unwrap();
+        assert_eq!(boat.position(), (10, 100));
+        assert_eq!(boat.vector(), (4, 10));
+        boat.do_action(Action::MoveForward, 7).unwrap();
+        assert_eq!(boat.position(), (38, 170));
+        assert_eq!(boat.vector(), (4, 10));
+        boat.do_action(Action::TurnRight, 90).unwrap();
+        assert_eq!(boat.position(), (38, 170));
+        assert_eq!(boat.vector(), (-10, 4));
+        boat.do_action(Action::MoveForward, 11).unwrap();
+        assert_eq!(boat.position(), (-72, 214));
+        assert_eq!(boat.vector(), (-10, 4));
     }
 
     #[test]
@@ -273,52 +626,339 @@ mod test {
     #[test]
     fn test_part1_rotation() {
         let mut boat = BoatPart1::new();
-        assert_eq!((boat.orientation_y, boat.orientation_x), (0, 1));
-        boat.do_action(Action::TurnLeft, 180);
-        assert_eq!((boat.orientation_y, boat.orientation_x), (0, -1));
-        boat.do_action(Action::TurnLeft, 180);
-        assert_eq!((boat.orientation_y, boat.orientation_x), (0, 1));
-        boat.do_action(Action::TurnRight, 90);
-        assert_eq!((boat.orientation_y, boat.orientation_x), (-1, 0));
-        boat.do_action(Action::TurnLeft, 180);
-        assert_eq!((boat.orientation_y, boat.orientation_x), (1, 0));
-        boat.do_action(Action::TurnRight, 90);
-        assert_eq!((boat.orientation_y, boat.orientation_x), (0, 1));
-        boat.do_action(Action::TurnRight, 270);
-        assert_eq!((boat.orientation_y, boat.orientation_x), (1, 0));
-        boat.do_action(Action::TurnLeft, 90);
-        assert_eq!((boat.orientation_y, boat.orientation_x), (0, -1));
-        boat.do_action(Action::TurnRight, 180);
-        assert_eq!((boat.orientation_y, boat.orientation_x), (0, 1));
-        boat.do_action(Action::TurnRight, 360);
-        assert_eq!((boat.orientation_y, boat.orientation_x), (0, 1));
+        assert_eq!(boat.vector(), (0, 1));
+        boat.do_action(Action::TurnLeft, 180).unwrap();
+        assert_eq!(boat.vector(), (0, -1));
+        boat.do_action(Action::TurnLeft, 180).unwrap();
+        assert_eq!(boat.vector(), (0, 1));
+        boat.do_action(Action::TurnRight, 90).unwrap();
+        assert_eq!(boat.vector(), (-1, 0));
+        boat.do_action(Action::TurnLeft, 180).unwrap();
+        assert_eq!(boat.vector(), (1, 0));
+        boat.do_action(Action::TurnRight, 90).unwrap();
+        assert_eq!(boat.vector(), (0, 1));
+        boat.do_action(Action::TurnRight, 270).unwrap();
+        assert_eq!(boat.vector(), (1, 0));
+        boat.do_action(Action::TurnLeft, 90).unwrap();
+        assert_eq!(boat.vector(), (0, -1));
+        boat.do_action(Action::TurnRight, 180).unwrap();
+        assert_eq!(boat.vector(), (0, 1));
+        boat.do_action(Action::TurnRight, 360).unwrap();
+        assert_eq!(boat.vector(), (0, 1));
     }
 
     #[test]
     fn test_part2_rotation() {
         let mut boat = BoatPart2::new();
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (1, 10));
-        boat.do_action(Action::TurnLeft, 180);
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (-1, -10));
-        boat.do_action(Action::TurnRight, 180);
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (1, 10));
-        boat.do_action(Action::TurnRight, 90);
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (-10, 1));
-        boat.do_action(Action::TurnLeft, 180);
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (10, -1));
-        boat.do_action(Action::TurnRight, 90);
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (1, 10));
-        boat.do_action(Action::TurnRight, 270);
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (10, -1));
-        boat.do_action(Action::TurnLeft, 90);
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (-1, -10));
-        boat.do_action(Action::TurnRight, 180);
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (1, 10));
-        boat.do_action(Action::TurnRight, 360);
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (1, 10));
-        boat.waypoint_y = 4;
-        boat.waypoint_x = 10;
-        boat.do_action(Action::TurnRight, 90);
-        assert_eq!((boat.waypoint_y, boat.waypoint_x), (-10, 4));
+        assert_eq!(boat.vector(), (1, 10));
+        boat.do_action(Action::TurnLeft, 180).unwrap();
+        assert_eq!(boat.vector(), (-1, -10));
+        boat.do_action(Action::TurnRight, 180).unwrap();
+        assert_eq!(boat.vector(), (1, 10));
+        boat.do_action(Action::TurnRight, 90).unwrap();
+        assert_eq!(boat.vector(), (-10, 1));
+        boat.do_action(Action::TurnLeft, 180).unwrap();
+        assert_eq!(boat.vector(), (10, -1));
+        boat.do_action(Action::TurnRight, 90).unwrap();
+        assert_eq!(boat.vector(), (1, 10));
+        boat.do_action(Action::TurnRight, 270).unwrap();
+        assert_eq!(boat.vector(), (10, -1));
+        boat.do_action(Action::TurnLeft, 90).unwrap();
+        assert_eq!(boat.vector(), (-1, -10));
+        boat.do_action(Action::TurnRight, 180).unwrap();
+        assert_eq!(boat.vector(), (1, 10));
+        boat.do_action(Action::TurnRight, 360).unwrap();
+        assert_eq!(boat.vector(), (1, 10));
+        boat.vector_y = 4;
+        boat.vector_x = 10;
+        boat.do_action(Action::TurnRight, 90).unwrap();
+        assert_eq!(boat.vector(), (-10, 4));
+    }
+
+    #[test]
+    fn test_part2_solve_example() {
+        assert_eq!(solve_part2(&parse_input("F10\nN3\nF7\nR90\nF11")), 286)
+    }
+
+    #[test]
+    fn test_quarter_turns() {
+        assert_eq!(quarter_turns(90), Ok(1));
+        assert_eq!(quarter_turns(-90), Ok(3));
+        assert_eq!(quarter_turns(450), Ok(1));
+        assert_eq!(quarter_turns(-720), Ok(0));
+        assert_eq!(quarter_turns(45), Err(NavigationError::InvalidTurn(45)));
+    }
+
+    #[test]
+    fn test_reject_non_right_angle_turns() {
+        let mut boat = BoatPart1::new();
+        assert_eq!(
+            boat.do_action(Action::TurnRight, 45),
+            Err(NavigationError::InvalidTurn(45))
+        );
+        assert_eq!(boat.vector(), (0, 1));
+
+        let mut boat = BoatPart2::new();
+        assert_eq!(
+            boat.do_action(Action::TurnLeft, 100),
+            Err(NavigationError::InvalidTurn(100))
+        );
+        assert_eq!(boat.vector(), (1, 10));
+
+        assert_eq!(
+            navigate(BoatPart1::new(), &parse_input("F10\nR30\nF11")).unwrap_err(),
+            NavigationError::InvalidTurn(30)
+        );
+    }
+
+    #[test]
+    fn test_trace_part1_example() {
+        let route = trace(BoatPart1::new(), &parse_input("F10\nN3\nF7\nR90\nF11")).unwrap();
+        assert_eq!(
+            route
+                .points()
+                .iter()
+                .map(|point| point.position)
+                .collect::<Vec<_>>(),
+            vec![(0, 0), (0, 10), (3, 10), (3, 17), (3, 17), (-8, 17)]
+        );
+        assert_eq!(route.points()[4].vector, (-1, 0));
+        assert_eq!(route.max_distance(), 25);
+        assert_eq!(route.distance_travelled(), 31);
+        assert_eq!(
+            route.bounding_box(),
+            BoundingBox {
+                min_y: -8,
+                min_x: 0,
+                max_y: 3,
+                max_x: 17
+            }
+        );
+    }
+
+    #[test]
+    fn test_trace_part2_example() {
+        let route = trace(BoatPart2::new(), &parse_input("F10\nN3\nF7\nR90\nF11")).unwrap();
+        assert_eq!(
+            route.points()[2],
+            RoutePoint {
+                position: (10, 100),
+                vector: (4, 10)
+            }
+        );
+        assert_eq!(route.max_distance(), 286);
+        assert_eq!(route.distance_travelled(), 362);
+        assert_eq!(
+            route.bounding_box(),
+            BoundingBox {
+                min_y: -72,
+                min_x: 0,
+                max_y: 38,
+                max_x: 214
+            }
+        );
+    }
+
+    #[test]
+    fn test_trace_invalid_turn() {
+        assert_eq!(
+            trace(BoatPart1::new(), &parse_input("F10\nL120")).unwrap_err(),
+            NavigationError::InvalidTurn(120)
+        );
+    }
+
+    #[test]
+    fn test_route_to_svg() {
+        let route = trace(BoatPart1::new(), &parse_input("F2\nN1")).unwrap();
+        assert_eq!(route.to_svg(), concat!(
+                "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -2 4 3\">\n",
+                "    <polyline points=\"0,0 2,0 2,-1\" fill=\"none\" stroke=\"black\" stroke-width=\"1\" vector-effect=\"non-scaling-stroke\"/>\n",
+                "    <circle cx=\"0\" cy=\"0\" r=\"0.5\" fill=\"green\"/>\n",
+                "    <circle cx=\"2\" cy=\"-1\" r=\"0.5\" fill=\"red\"/>\n",
+                "</svg>\n"
+            ));
+    }
+
+    #[test]
+    fn test_parse_extended() {
+        assert_eq!(
+            parse("# warm up\nNE5\nSW2 # back a bit\n\nrepeat 2 {\n  F1 R90\n}\nG"),
+            Ok(vec![
+                (Action::MoveNorthEast, 5),
+                (Action::MoveSouthWest, 2),
+                (Action::MoveForward, 1),
+                (Action::TurnRight, 90),
+                (Action::MoveForward, 1),
+                (Action::TurnRight, 90),
+                (Action::GoToWaypoint, 1),
+            ])
+        );
+        assert_eq!(
+            parse("repeat 2 { N1 repeat 3 { E1 } }\nrepeat 0 { F10 }"),
+            Ok(vec![
+                (Action::MoveNorth, 1),
+                (Action::MoveEast, 1),
+                (Action::MoveEast, 1),
+                (Action::MoveEast, 1),
+                (Action::MoveNorth, 1),
+                (Action::MoveEast, 1),
+                (Action::MoveEast, 1),
+                (Action::MoveEast, 1),
+            ])
+        );
+        assert_eq!(parse("# nothing to do\n"), Ok(vec![]));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("F10\nX3"),
+            Err(ParseError::UnknownCommand {
+                line: 2,
+                command: "X3".to_owned()
+            })
+        );
+        assert_eq!(
+            parse("F"),
+            Err(ParseError::InvalidValue {
+                line: 1,
+                command: "F".to_owned()
+            })
+        );
+        assert_eq!(
+            parse("N-3"),
+            Err(ParseError::InvalidValue {
+                line: 1,
+                command: "N-3".to_owned()
+            })
+        );
+        assert_eq!(
+            parse("G2"),
+            Err(ParseError::InvalidValue {
+                line: 1,
+                command: "G2".to_owned()
+            })
+        );
+        assert_eq!(
+            parse("repeat x {"),
+            Err(ParseError::InvalidRepeatCount {
+                line: 1,
+                count: "x".to_owned()
+            })
+        );
+        assert_eq!(
+            parse("repeat 2 F10"),
+            Err(ParseError::MissingBrace { line: 1 })
+        );
+        assert_eq!(
+            parse("F1\nrepeat 2 {\nF10"),
+            Err(ParseError::UnclosedRepeat { line: 2 })
+        );
+        assert_eq!(parse("F1\n}"), Err(ParseError::UnexpectedBrace { line: 2 }));
+        assert_eq!(
+            parse("F1\nrepeat 100000 {\nrepeat 100000 { F1 }\n}"),
+            Err(ParseError::TooLarge { line: 2 })
+        );
+        assert_eq!(
+            parse(&format!("repeat {} {{ F1 }}\nF1", usize::MAX)),
+            Err(ParseError::TooLarge { line: 1 })
+        );
+        assert_eq!(parse(&format!("repeat {} {{ }}", usize::MAX)), Ok(vec![]));
+        assert_eq!(
+            parse(&format!("repeat {} {{ F1 }}", MAX_ACTIONS))
+                .unwrap()
+                .len(),
+            MAX_ACTIONS
+        );
+        assert_eq!(
+            ParseError::UnknownCommand {
+                line: 2,
+                command: "X3".to_owned()
+            }
+            .to_string(),
+            "line 2: unknown command `X3`"
+        );
+    }
+
+    #[test]
+    fn test_extended_actions() {
+        let mut boat = BoatPart1::new();
+        boat.do_action(Action::MoveNorthEast, 3).unwrap();
+        assert_eq!(boat.position(), (3, 3));
+        assert_eq!(
+            boat.do_action(Action::GoToWaypoint, 1),
+            Err(NavigationError::NoWaypoint)
+        );
+        assert_eq!(boat.position(), (3, 3));
+
+        let mut boat = BoatPart2::new();
+        boat.do_action(Action::MoveSouthWest, 2).unwrap();
+        assert_eq!(boat.vector(), (-1, 8));
+        boat.do_action(Action::GoToWaypoint, 1).unwrap();
+        assert_eq!(boat.position(), (-1, 8));
+        assert_eq!(boat.vector(), (0, 0));
+        boat.do_action(Action::MoveForward, 10).unwrap();
+        assert_eq!(boat.position(), (-1, 8));
+        boat.do_action(Action::MoveNorth, 2).unwrap();
+        boat.do_action(Action::GoToWaypoint, 1).unwrap();
+        assert_eq!(boat.position(), (1, 8));
+    }
+
+    #[test]
+    fn test_navigation_overflow() {
+        let input = parse("repeat 3 { F2000000000 }").unwrap();
+        assert_eq!(
+            navigate(BoatPart1::new(), &input).unwrap_err(),
+            NavigationError::Overflow
+        );
+        assert_eq!(
+            trace(BoatPart2::new(), &input).unwrap_err(),
+            NavigationError::Overflow
+        );
+
+        let mut boat = BoatPart1::new();
+        boat.do_action(Action::MoveNorthEast, 2000000000).unwrap();
+        assert_eq!(boat.get_dist_from_start(), 4000000000);
+        assert_eq!(
+            boat.do_action(Action::MoveNorth, 2000000000),
+            Err(NavigationError::Overflow)
+        );
+        assert_eq!(boat.position(), (2000000000, 2000000000));
+
+        let mut boat = BoatPart2::new();
+        assert_eq!(
+            boat.do_action(Action::MoveSouthWest, i32::MIN),
+            Err(NavigationError::Overflow)
+        );
+        boat.do_action(Action::MoveEast, i32::MAX - 10).unwrap();
+        assert_eq!(
+            boat.do_action(Action::MoveEast, 1),
+            Err(NavigationError::Overflow)
+        );
+        boat.do_action(Action::GoToWaypoint, 1).unwrap();
+        assert_eq!(boat.position(), (1, i32::MAX));
+        boat.do_action(Action::MoveWest, 1).unwrap();
+        boat.do_action(Action::MoveWest, i32::MAX).unwrap();
+        assert_eq!(boat.vector(), (0, i32::MIN));
+        assert_eq!(
+            boat.do_action(Action::TurnRight, 90),
+            Err(NavigationError::Overflow)
+        );
+        assert_eq!(boat.vector(), (0, i32::MIN));
+    }
+
+    #[test]
+    fn test_route_near_the_i32_bounds() {
+        let route = trace(
+            BoatPart1::new(),
+            &parse("NE2147483647\nSW2147483647\nS2147483647\nF1").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(route.max_distance(), 4294967294);
+        assert_eq!(route.distance_travelled(), 10737418236);
+        assert_eq!(route.bounding_box().height(), 4294967294);
+        assert!(route
+            .to_svg()
+            .contains("viewBox=\"-214748365 -2362232012 2576980377 4724464024\""));
     }
 }
@@ -1,45 +1,244 @@
-use std::collections::HashSet;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::Read;
 
-pub fn solve_part1(input: &str) -> usize {
-    input
-        .split("\n\n")
-        .map(|group| {
-            group
+fn question_bit(question: char) -> u32 {
+    1 << (question as u8 - b'a')
+}
+
+// one bit per question, 'a' is the lowest bit
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Answers(u32);
+
+impl Answers {
+    pub const NONE: Answers = Answers(0);
+    pub const ALL: Answers = Answers((1 << 26) - 1);
+
+    pub fn from_person(person: &str) -> Answers {
+        Answers(
+            person
                 .chars()
                 .filter(|c| c.is_ascii_lowercase())
-                .collect::<HashSet<char>>()
-                .len()
+                .fold(0, |mask, c| mask | question_bit(c)),
+        )
+    }
+
+    pub fn contains(self, question: char) -> bool {
+        question.is_ascii_lowercase() && self.0 & question_bit(question) != 0
+    }
+
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn union(self, other: Answers) -> Answers {
+        Answers(self.0 | other.0)
+    }
+
+    pub fn intersection(self, other: Answers) -> Answers {
+        Answers(self.0 & other.0)
+    }
+
+    pub fn symmetric_difference(self, other: Answers) -> Answers {
+        Answers(self.0 ^ other.0)
+    }
+
+    pub fn questions(self) -> impl Iterator<Item = char> {
+        ('a'..='z').filter(move |c| self.contains(*c))
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Group {
+    people: Vec<Answers>,
+}
+
+impl Group {
+    pub fn parse(group: &str) -> Group {
+        Group {
+            people: group
+                .lines()
+                .filter(|person| !person.is_empty())
+                .map(Answers::from_person)
+                .collect(),
+        }
+    }
+
+    pub fn people(&self) -> &[Answers] {
+        &self.people
+    }
+
+    pub fn union(&self) -> Answers {
+        self.people
+            .iter()
+            .fold(Answers::NONE, |acc, person| acc.union(*person))
+    }
+
+    pub fn intersection(&self) -> Answers {
+        if self.people.is_empty() {
+            return Answers::NONE;
+        }
+        self.people
+            .iter()
+            .fold(Answers::ALL, |acc, person| acc.intersection(*person))
+    }
+
+    // questions answered "yes" by an odd number of people in the group
+    pub fn symmetric_difference(&self) -> Answers {
+        self.people.iter().fold(Answers::NONE, |acc, person| {
+            acc.symmetric_difference(*person)
         })
+    }
+
+    pub fn count_answering(&self, question: char) -> usize {
+        self.people
+            .iter()
+            .filter(|person| person.contains(question))
+            .count()
+    }
+
+    pub fn answered_by_at_least(&self, k: usize) -> Answers {
+        if k == 0 {
+            return Answers::ALL;
+        }
+        Answers(
+            ('a'..='z')
+                .filter(|c| self.count_answering(*c) >= k)
+                .fold(0, |mask, c| mask | question_bit(c)),
+        )
+    }
+}
+
+pub fn parse_groups(input: &str) -> Vec<Group> {
+    input.split("\n\n").map(Group::parse).collect()
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct QuestionStats {
+    pub question: char,
+    pub people: usize,
+    pub groups: usize,
+    pub unanimous_groups: usize,
+}
+
+impl QuestionStats {
+    // share of the groups answering this question where everyone answered it
+    pub fn unanimity_rate(&self) -> f64 {
+        if self.groups == 0 {
+            0.0
+        } else {
+            self.unanimous_groups as f64 / self.groups as f64
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Report {
+    pub questions: Vec<QuestionStats>,
+    pub group_sizes: BTreeMap<usize, usize>, // group size -> number of groups
+    pub total_people: usize,
+    pub total_groups: usize,
+}
+
+impl Report {
+    pub fn from_groups(groups: &[Group]) -> Report {
+        let questions = ('a'..='z')
+            .map(|question| QuestionStats {
+                question,
+                people: groups
+                    .iter()
+                    .map(|group| group.count_answering(question))
+                    .sum(),
+                groups: groups
+                    .iter()
+                    .filter(|group| group.union().contains(question))
+                    .count(),
+                unanimous_groups: groups
+                    .iter()
+                    .filter(|group| group.intersection().contains(question))
+                    .count(),
+            })
+            .collect();
+
+        let mut group_sizes = BTreeMap::new();
+        for group in groups {
+            *group_sizes.entry(group.people().len()).or_insert(0) += 1;
+        }
+
+        Report {
+            questions,
+            group_sizes,
+            total_people: groups.iter().map(|group| group.people().len()).sum(),
+            total_groups: groups.len(),
+        }
+    }
+
+    pub fn most_popular(&self) -> Vec<&QuestionStats> {
+        let mut questions = self.questions.iter().collect::<Vec<_>>();
+        questions.sort_by(|a, b| b.people.cmp(&a.people).then(a.question.cmp(&b.question)));
+        questions
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("question,people,groups,unanimous_groups,unanimity_rate\n");
+        for stats in &self.questions {
+            csv += &format!(
+                "{},{},{},{},{:.4}\n",
+                stats.question,
+                stats.people,
+                stats.groups,
+                stats.unanimous_groups,
+                stats.unanimity_rate()
+            );
+        }
+        csv
+    }
+
+    pub fn group_sizes_to_csv(&self) -> String {
+        let mut csv = String::from("group_size,groups\n");
+        for (size, count) in &self.group_sizes {
+            csv += &format!("{},{}\n", size, count);
+        }
+        csv
+    }
+}
+
+pub fn solve_part1(input: &str) -> usize {
+    parse_groups(input)
+        .iter()
+        .map(|group| group.union().len())
         .sum()
 }
 
 pub fn solve_part1_iter_over_alpha(input: &str) -> usize {
     input
         .split("\n\n")
-        .map(|group| ('a'..'z').filter(|c| group.contains(|g| *c == g)).count())
+        .map(|group| ('a'..='z').filter(|c| group.contains(*c)).count())
         .sum()
 }
 
 pub fn solve_part2(input: &str) -> usize {
-    input
-        .split("\n\n")
-        .map(|group| {
-            let group = group
-                .lines()
-                .map(|person| person.to_owned())
-                .collect::<Vec<String>>();
-            group[0]
-                .chars()
-                .filter(|c| {
-                    group
-                        .iter()
-                        .skip(1)
-                        .all(|person| person.contains(|p| *c == p))
-                })
-                .count()
-        })
+    parse_groups(input)
+        .iter()
+        .map(|group| group.intersection().len())
+        .sum()
+}
+
+pub fn solve_symmetric_difference(input: &str) -> usize {
+    parse_groups(input)
+        .iter()
+        .map(|group| group.symmetric_difference().len())
+        .sum()
+}
+
+pub fn solve_answered_by_at_least(input: &str, k: usize) -> usize {
+    parse_groups(input)
+        .iter()
+        .map(|group| group.answered_by_at_least(k).len())
         .sum()
 }
 
@@ -48,8 +247,8 @@ pub fn solve_part2_iter_over_alpha(input: &str) -> usize {
         .split("\n\n")
         .map(|group| {
             let nbperson = group.lines().count();
-            ('a'..'z')
-                .filter(|c| group.matches(|g| *c == g).count() == nbperson)
+            ('a'..='z')
+                .filter(|c| group.matches(*c).count() == nbperson)
                 .count()
         })
         .sum()
@@ -69,6 +268,19 @@ pub fn part2() {
     println!("{}", solve_part2(&input));
 }
 
+// one CSV per call, so the output can be redirected straight into a file
+pub fn report(args: &[&str]) {
+    let mut file = File::open("input/2020/day6.txt").unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+    let report = Report::from_groups(&parse_groups(&input));
+    match args {
+        ["questions"] => print!("{}", report.to_csv()),
+        ["sizes"] => print!("{}", report.group_sizes_to_csv()),
+        _ => eprintln!("usage: day6 report questions | sizes"),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -80,4 +292,82 @@ mod test {
             11
         );
     }
+
+    #[test]
+    fn solve_part2_example() {
+        assert_eq!(
+            solve_part2("abc\n\na\nb\nc\n\nab\nac\n\na\na\na\na\n\nb"),
+            6
+        );
+    }
+
+    #[test]
+    fn test_iter_over_alpha_includes_z() {
+        assert_eq!(solve_part1_iter_over_alpha("z\nyz\n\nz"), 3);
+        assert_eq!(solve_part2_iter_over_alpha("z\nyz\n\nz"), 2);
+    }
+
+    #[test]
+    fn test_group_set_algebra() {
+        let group = Group::parse("abz\nbcz\nbz\n");
+        assert_eq!(group.union().questions().collect::<String>(), "abcz");
+        assert_eq!(group.intersection().questions().collect::<String>(), "bz");
+        assert_eq!(
+            group.symmetric_difference().questions().collect::<String>(),
+            "abcz"
+        );
+        assert_eq!(
+            group
+                .answered_by_at_least(2)
+                .questions()
+                .collect::<String>(),
+            "bz"
+        );
+        assert_eq!(group.answered_by_at_least(4), Answers::NONE);
+    }
+
+    #[test]
+    fn test_answered_by_at_least_example() {
+        let input = "abc\n\na\nb\nc\n\nab\nac\n\na\na\na\na\n\nb";
+        assert_eq!(solve_answered_by_at_least(input, 1), solve_part1(input));
+        assert_eq!(solve_answered_by_at_least(input, 2), 2);
+    }
+
+    #[test]
+    fn test_report_example() {
+        let report =
+            Report::from_groups(&parse_groups("abc\n\na\nb\nc\n\nab\nac\n\na\na\na\na\n\nb"));
+        assert_eq!(report.total_groups, 5);
+        assert_eq!(report.total_people, 11);
+        assert_eq!(
+            report.questions[0],
+            QuestionStats {
+                question: 'a',
+                people: 8,
+                groups: 4,
+                unanimous_groups: 3,
+            }
+        );
+        assert_eq!(report.questions[0].unanimity_rate(), 0.75);
+        assert_eq!(report.most_popular()[0].question, 'a');
+        assert_eq!(
+            report.group_sizes.iter().collect::<Vec<_>>(),
+            vec![(&1, &2), (&2, &1), (&3, &1), (&4, &1)]
+        );
+    }
+
+    #[test]
+    fn test_report_csv() {
+        let report = Report::from_groups(&parse_groups("ab\nb"));
+        let csv = report.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("question,people,groups,unanimous_groups,unanimity_rate")
+        );
+        assert_eq!(lines.next(), Some("a,1,1,0,0.0000"));
+        assert_eq!(lines.next(), Some("b,2,1,1,1.0000"));
+        assert_eq!(lines.next(), Some("c,0,0,0,0.0000"));
+        assert_eq!(report.group_sizes_to_csv(), "group_size,groups\n2,1\n");
+    }
 }
@@ -1,43 +1,349 @@
 use regex::Regex;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::fs::File;
 use std::io::Read;
 
-pub fn solve_part1(rules: &[(String, Vec<(usize, String)>)]) -> usize {
-    let mut found = HashSet::new();
-    find_rules_for_bag("shiny gold", rules, &mut found);
-    found.len()
+pub type BagId = usize;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum RuleError {
+    Cycle(Vec<String>), // the first and last colours of the path are the same
+    UndefinedColour(String),
+    DuplicateDefinition(String),
+    Overflow(String),
 }
 
-fn find_rules_for_bag(
-    bag: &str,
-    rules: &[(String, Vec<(usize, String)>)],
-    already_found: &mut HashSet<String>,
-) {
-    rules
-        .iter()
-        .filter(|(_, value)| value.iter().any(|(_, other_bag)| bag == other_bag))
-        .for_each(|(key, _)| {
-            if !already_found.contains(key) {
-                already_found.insert(key.clone());
-                find_rules_for_bag(key, rules, already_found);
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleError::Cycle(path) => write!(f, "cycle: {}", path.join(" -> ")),
+            RuleError::UndefinedColour(colour) => write!(f, "no rule for {} bags", colour),
+            RuleError::DuplicateDefinition(colour) => {
+                write!(f, "{} bags are defined more than once", colour)
             }
-        });
+            RuleError::Overflow(colour) => write!(f, "bag count overflows for {} bags", colour),
+        }
+    }
 }
 
-pub fn solve_part2(rules: &[(String, Vec<(usize, String)>)]) -> usize {
-    find_total_bags_for_bag("shiny gold", rules) - 1
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct BagGraph {
+    colours: Vec<String>,
+    ids: HashMap<String, BagId>,
+    contents: Vec<Vec<(usize, BagId)>>, // bag -> bags it directly contains
+    containers: Vec<Vec<(usize, BagId)>>, // bag -> bags directly containing it
+    defined: Vec<bool>,
+    duplicates: Vec<BagId>,
 }
 
-fn find_total_bags_for_bag(bag: &str, rules: &[(String, Vec<(usize, String)>)]) -> usize {
-    1 + rules
-        .iter()
-        .find(|(key, _)| key == bag)
+impl BagGraph {
+    pub fn new() -> BagGraph {
+        BagGraph::default()
+    }
+
+    pub fn from_rules(rules: &[(String, Vec<(usize, String)>)]) -> BagGraph {
+        let mut graph = BagGraph::new();
+        for (bag, contents) in rules {
+            graph.add_rule(bag, contents);
+        }
+        graph
+    }
+
+    pub fn add_rule(&mut self, bag: &str, contents: &[(usize, String)]) {
+        let bag = self.intern(bag);
+        if self.defined[bag] {
+            // keep the first definition, the duplicate is reported by `validate`
+            self.duplicates.push(bag);
+            return;
+        }
+        self.defined[bag] = true;
+        for (count, other_bag) in contents {
+            let other_bag = self.intern(other_bag);
+            self.contents[bag].push((*count, other_bag));
+            self.containers[other_bag].push((*count, bag));
+        }
+    }
+
+    pub fn intern(&mut self, colour: &str) -> BagId {
+        if let Some(id) = self.ids.get(colour) {
+            return *id;
+        }
+        let id = self.colours.len();
+        self.colours.push(colour.to_owned());
+        self.ids.insert(colour.to_owned(), id);
+        self.contents.push(Vec::new());
+        self.containers.push(Vec::new());
+        self.defined.push(false);
+        id
+    }
+
+    pub fn id(&self, colour: &str) -> Option<BagId> {
+        self.ids.get(colour).copied()
+    }
+
+    pub fn colour(&self, bag: BagId) -> &str {
+        &self.colours[bag]
+    }
+
+    pub fn len(&self) -> usize {
+        self.colours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colours.is_empty()
+    }
+
+    pub fn contents(&self, bag: BagId) -> &[(usize, BagId)] {
+        &self.contents[bag]
+    }
+
+    pub fn containers(&self, bag: BagId) -> &[(usize, BagId)] {
+        &self.containers[bag]
+    }
+
+    pub fn ancestors(&self, bag: BagId) -> HashSet<BagId> {
+        self.walk(bag, &self.containers)
+    }
+
+    pub fn descendants(&self, bag: BagId) -> HashSet<BagId> {
+        self.walk(bag, &self.contents)
+    }
+
+    fn walk(&self, bag: BagId, edges: &[Vec<(usize, BagId)>]) -> HashSet<BagId> {
+        let mut found = HashSet::new();
+        let mut to_visit = vec![bag];
+        while let Some(bag) = to_visit.pop() {
+            for (_, other_bag) in &edges[bag] {
+                if found.insert(*other_bag) {
+                    to_visit.push(*other_bag);
+                }
+            }
+        }
+        found
+    }
+
+    pub fn is_defined(&self, bag: BagId) -> bool {
+        self.defined[bag]
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<RuleError>> {
+        let mut errors = self
+            .duplicates
+            .iter()
+            .map(|bag| RuleError::DuplicateDefinition(self.colour(*bag).to_owned()))
+            .collect::<Vec<_>>();
+        errors.extend(
+            (0..self.len())
+                .filter(|bag| !self.defined[*bag])
+                .map(|bag| RuleError::UndefinedColour(self.colour(bag).to_owned())),
+        );
+        errors.extend(self.find_cycles().into_iter().map(RuleError::Cycle));
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    // one cycle per back edge met during a depth first walk of every bag
+    pub fn find_cycles(&self) -> Vec<Vec<String>> {
+        let mut cycles = Vec::new();
+        let mut visited = vec![false; self.len()];
+        let mut on_path = vec![false; self.len()];
+
+        for root in 0..self.len() {
+            if visited[root] {
+                continue;
+            }
+            visited[root] = true;
+            on_path[root] = true;
+            let mut stack = vec![(root, 0)];
+            while let Some(&(bag, next)) = stack.last() {
+                match self.contents[bag].get(next) {
+                    Some((_, other_bag)) => {
+                        stack.last_mut().unwrap().1 += 1;
+                        if on_path[*other_bag] {
+                            cycles.push(self.cycle_path(&stack, *other_bag));
+                        } else if !visited[*other_bag] {
+                            visited[*other_bag] = true;
+                            on_path[*other_bag] = true;
+                            stack.push((*other_bag, 0));
+                        }
+                    }
+                    None => {
+                        on_path[bag] = false;
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        cycles
+    }
+
+    fn cycle_path(&self, stack: &[(BagId, usize)], back_to: BagId) -> Vec<String> {
+        stack
+            .iter()
+            .skip_while(|(bag, _)| *bag != back_to)
+            .map(|(bag, _)| *bag)
+            .chain(std::iter::once(back_to))
+            .map(|bag| self.colour(bag).to_owned())
+            .collect()
+    }
+
+    // iterative and memoized so that deep or cyclic rules can't blow the stack
+    fn count_bags_including_self(&self, bag: BagId) -> Result<usize, RuleError> {
+        let mut totals: Vec<Option<usize>> = vec![None; self.len()];
+        let mut on_path = vec![false; self.len()];
+        let mut stack = vec![(bag, 0)];
+        on_path[bag] = true;
+
+        while let Some(&(current, next)) = stack.last() {
+            if !self.defined[current] {
+                return Err(RuleError::UndefinedColour(self.colour(current).to_owned()));
+            }
+            match self.contents[current].get(next) {
+                Some((_, other_bag)) => {
+                    stack.last_mut().unwrap().1 += 1;
+                    if totals[*other_bag].is_some() {
+                        continue;
+                    }
+                    if on_path[*other_bag] {
+                        return Err(RuleError::Cycle(self.cycle_path(&stack, *other_bag)));
+                    }
+                    on_path[*other_bag] = true;
+                    stack.push((*other_bag, 0));
+                }
+                None => {
+                    let total = self.contents[current]
+                        .iter()
+                        .try_fold(1usize, |acc, (count, other_bag)| {
+                            acc.checked_add(count.checked_mul(totals[*other_bag]?)?)
+                        })
+                        .ok_or_else(|| RuleError::Overflow(self.colour(current).to_owned()))?;
+                    totals[current] = Some(total);
+                    on_path[current] = false;
+                    stack.pop();
+                }
+            }
+        }
+
+        Ok(totals[bag].unwrap())
+    }
+
+    pub fn to_dot(&self) -> String {
+        self.dot_for(&(0..self.len()).collect())
+    }
+
+    // `colour` and every bag it eventually contains
+    pub fn to_dot_from(&self, colour: &str) -> Option<String> {
+        let bag = self.id(colour)?;
+        let mut bags = self.descendants(bag);
+        bags.insert(bag);
+        Some(self.dot_for(&bags))
+    }
+
+    // `colour` and every bag that can eventually contain it
+    pub fn to_dot_to(&self, colour: &str) -> Option<String> {
+        let bag = self.id(colour)?;
+        let mut bags = self.ancestors(bag);
+        bags.insert(bag);
+        Some(self.dot_for(&bags))
+    }
+
+    fn dot_for(&self, bags: &HashSet<BagId>) -> String {
+        let quote = |bag: BagId| format!("\"{}\"", self.colour(bag).replace('"', "\\\""));
+        let mut dot = String::from("digraph bags {\n");
+        for bag in (0..self.len()).filter(|bag| bags.contains(bag)) {
+            dot += &format!("    {};\n", quote(bag));
+        }
+        for bag in (0..self.len()).filter(|bag| bags.contains(bag)) {
+            for (count, other_bag) in self.contents(bag) {
+                if bags.contains(other_bag) {
+                    dot += &format!(
+                        "    {} -> {} [label=\"{}\"];\n",
+                        quote(bag),
+                        quote(*other_bag),
+                        count
+                    );
+                }
+            }
+        }
+        dot += "}\n";
+        dot
+    }
+
+    pub fn bags_containing(&self, colour: &str) -> Vec<&str> {
+        let mut colours = match self.id(colour) {
+            Some(bag) => self
+                .ancestors(bag)
+                .into_iter()
+                .map(|bag| self.colour(bag))
+                .collect::<Vec<_>>(),
+            None => Vec::new(),
+        };
+        colours.sort_unstable();
+        colours
+    }
+
+    pub fn total_bags_inside(&self, colour: &str) -> Result<usize, RuleError> {
+        let bag = self
+            .id(colour)
+            .ok_or_else(|| RuleError::UndefinedColour(colour.to_owned()))?;
+        Ok(self.count_bags_including_self(bag)? - 1)
+    }
+
+    pub fn direct_contents(&self, colour: &str) -> Option<Vec<(usize, &str)>> {
+        Some(
+            self.contents(self.id(colour)?)
+                .iter()
+                .map(|(count, bag)| (*count, self.colour(*bag)))
+                .collect(),
+        )
+    }
+
+    // every chain of bags going from `outer` down to `inner`, both included
+    pub fn containment_paths(&self, outer: &str, inner: &str) -> Vec<Vec<&str>> {
+        let mut paths = Vec::new();
+        if let (Some(outer), Some(inner)) = (self.id(outer), self.id(inner)) {
+            self.find_paths(outer, inner, &mut vec![outer], &mut paths);
+        }
+        paths
+    }
+
+    fn find_paths<'a>(
+        &'a self,
+        bag: BagId,
+        inner: BagId,
+        path: &mut Vec<BagId>,
+        paths: &mut Vec<Vec<&'a str>>,
+    ) {
+        for (_, other_bag) in self.contents(bag) {
+            if path.contains(other_bag) {
+                continue;
+            }
+            path.push(*other_bag);
+            if *other_bag == inner {
+                paths.push(path.iter().map(|bag| self.colour(*bag)).collect());
+            } else {
+                self.find_paths(*other_bag, inner, path, paths);
+            }
+            path.pop();
+        }
+    }
+}
+
+pub fn solve_part1(rules: &[(String, Vec<(usize, String)>)]) -> usize {
+    BagGraph::from_rules(rules)
+        .bags_containing("shiny gold")
+        .len()
+}
+
+pub fn solve_part2(rules: &[(String, Vec<(usize, String)>)]) -> usize {
+    BagGraph::from_rules(rules)
+        .total_bags_inside("shiny gold")
         .unwrap()
-        .1
-        .iter()
-        .map(|(count, bag)| count * find_total_bags_for_bag(bag, rules))
-        .sum::<usize>()
 }
 
 pub fn parse_part1(input: &str) -> Vec<(String, Vec<(usize, String)>)> {
@@ -80,6 +386,50 @@ pub fn part2() {
     println!("{}", solve_part2(&parse_part1(&input)));
 }
 
+pub fn query(args: &[&str]) {
+    let mut file = File::open("input/2020/day7.txt").unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+    let graph = BagGraph::from_rules(&parse_part1(&input));
+
+    match args {
+        ["containers", colour] => graph
+            .bags_containing(colour)
+            .iter()
+            .for_each(|bag| println!("{}", bag)),
+        ["count", colour] => match graph.total_bags_inside(colour) {
+            Ok(total) => println!("{}", total),
+            Err(error) => eprintln!("{}", error),
+        },
+        ["validate"] => match graph.validate() {
+            Ok(()) => println!("ok"),
+            Err(errors) => errors.iter().for_each(|error| println!("{}", error)),
+        },
+        ["contents", colour] => match graph.direct_contents(colour) {
+            Some(contents) => contents
+                .iter()
+                .for_each(|(count, bag)| println!("{} {}", count, bag)),
+            None => eprintln!("unknown bag colour: {}", colour),
+        },
+        ["paths", outer, inner] => graph
+            .containment_paths(outer, inner)
+            .iter()
+            .for_each(|path| println!("{}", path.join(" -> "))),
+        ["dot"] => print!("{}", graph.to_dot()),
+        ["dot", "from", colour] => match graph.to_dot_from(colour) {
+            Some(dot) => print!("{}", dot),
+            None => eprintln!("unknown bag colour: {}", colour),
+        },
+        ["dot", "to", colour] => match graph.to_dot_to(colour) {
+            Some(dot) => print!("{}", dot),
+            None => eprintln!("unknown bag colour: {}", colour),
+        },
+        _ => eprintln!(
+            "usage: day7 containers <colour> | count <colour> | contents <colour> | paths <outer> <inner> | validate | dot [from|to <colour>]"
+        ),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -148,4 +498,128 @@ mod test {
             expected
         );
     }
+
+    #[test]
+    fn test_bag_graph_adjacency() {
+        let graph = BagGraph::from_rules(&parse_part1("light red bags contain 1 bright white bag, 2 muted yellow bags.\nbright white bags contain 1 shiny gold bag.\nmuted yellow bags contain 2 shiny gold bags, 9 faded blue bags.\nshiny gold bags contain no other bags.\nfaded blue bags contain no other bags."));
+        assert_eq!(graph.len(), 5);
+        let red = graph.id("light red").unwrap();
+        let white = graph.id("bright white").unwrap();
+        let yellow = graph.id("muted yellow").unwrap();
+        let gold = graph.id("shiny gold").unwrap();
+        assert_eq!(graph.colour(gold), "shiny gold");
+        assert_eq!(graph.contents(red), &[(1, white), (2, yellow)]);
+        assert_eq!(graph.containers(gold), &[(1, white), (2, yellow)]);
+        assert_eq!(
+            graph.ancestors(gold),
+            [red, white, yellow].iter().copied().collect()
+        );
+        assert_eq!(graph.id("dark olive"), None);
+    }
+
+    #[test]
+    fn test_bag_queries() {
+        let graph = BagGraph::from_rules(&parse_part1("light red bags contain 1 bright white bag, 2 muted yellow bags.\ndark orange bags contain 3 bright white bags, 4 muted yellow bags.\nbright white bags contain 1 shiny gold bag.\nmuted yellow bags contain 2 shiny gold bags, 9 faded blue bags.\nshiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.\ndark olive bags contain 3 faded blue bags, 4 dotted black bags.\nvibrant plum bags contain 5 faded blue bags, 6 dotted black bags.\nfaded blue bags contain no other bags.\ndotted black bags contain no other bags."));
+        assert_eq!(
+            graph.bags_containing("bright white"),
+            vec!["dark orange", "light red"]
+        );
+        assert_eq!(graph.bags_containing("light red"), Vec::<&str>::new());
+        assert_eq!(graph.total_bags_inside("dark olive"), Ok(7));
+        assert_eq!(graph.total_bags_inside("faded blue"), Ok(0));
+        assert_eq!(
+            graph.total_bags_inside("neon pink"),
+            Err(RuleError::UndefinedColour("neon pink".to_string()))
+        );
+        assert_eq!(
+            graph.direct_contents("muted yellow"),
+            Some(vec![(2, "shiny gold"), (9, "faded blue")])
+        );
+        assert_eq!(
+            graph.containment_paths("light red", "shiny gold"),
+            vec![
+                vec!["light red", "bright white", "shiny gold"],
+                vec!["light red", "muted yellow", "shiny gold"],
+            ]
+        );
+        assert_eq!(
+            graph.containment_paths("shiny gold", "light red"),
+            Vec::<Vec<&str>>::new()
+        );
+    }
+
+    #[test]
+    fn test_validate_example() {
+        let graph = BagGraph::from_rules(&parse_part1("light red bags contain 1 bright white bag, 2 muted yellow bags.\ndark orange bags contain 3 bright white bags, 4 muted yellow bags.\nbright white bags contain 1 shiny gold bag.\nmuted yellow bags contain 2 shiny gold bags, 9 faded blue bags.\nshiny gold bags contain 1 dark olive bag, 2 vibrant plum bags.\ndark olive bags contain 3 faded blue bags, 4 dotted black bags.\nvibrant plum bags contain 5 faded blue bags, 6 dotted black bags.\nfaded blue bags contain no other bags.\ndotted black bags contain no other bags."));
+        assert_eq!(graph.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_invalid_rules() {
+        let graph = BagGraph::from_rules(&parse_part1("light red bags contain 1 bright white bag.\nbright white bags contain 2 muted yellow bags.\nmuted yellow bags contain 1 light red bag, 3 faded blue bags.\nlight red bags contain no other bags."));
+        assert_eq!(
+            graph.validate(),
+            Err(vec![
+                RuleError::DuplicateDefinition("light red".to_string()),
+                RuleError::UndefinedColour("faded blue".to_string()),
+                RuleError::Cycle(vec![
+                    "light red".to_string(),
+                    "bright white".to_string(),
+                    "muted yellow".to_string(),
+                    "light red".to_string(),
+                ]),
+            ])
+        );
+        assert_eq!(
+            graph.total_bags_inside("bright white"),
+            Err(RuleError::Cycle(vec![
+                "bright white".to_string(),
+                "muted yellow".to_string(),
+                "light red".to_string(),
+                "bright white".to_string(),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_count_undefined_and_overflow() {
+        let graph = BagGraph::from_rules(&parse_part1(
+            "light red bags contain 2 bright white bags.\nbright white bags contain 3 faded blue bags.",
+        ));
+        assert_eq!(
+            graph.total_bags_inside("light red"),
+            Err(RuleError::UndefinedColour("faded blue".to_string()))
+        );
+
+        let graph = BagGraph::from_rules(&parse_part1("light red bags contain 18446744073709551615 bright white bags.\nbright white bags contain 2 faded blue bags.\nfaded blue bags contain no other bags."));
+        assert_eq!(
+            graph.total_bags_inside("light red"),
+            Err(RuleError::Overflow("light red".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_count_deep_chain() {
+        let mut graph = BagGraph::new();
+        for i in 0..100_000 {
+            graph.add_rule(&format!("bag {}", i), &[(1, format!("bag {}", i + 1))]);
+        }
+        graph.add_rule("bag 100000", &[]);
+        assert_eq!(graph.total_bags_inside("bag 0"), Ok(100_000));
+    }
+
+    #[test]
+    fn test_to_dot() {
+        let graph = BagGraph::from_rules(&parse_part1("light red bags contain 1 bright white bag, 2 muted yellow bags.\nbright white bags contain 1 shiny gold bag.\nmuted yellow bags contain 2 shiny gold bags, 9 faded blue bags.\nshiny gold bags contain no other bags.\nfaded blue bags contain no other bags."));
+        assert_eq!(
+            graph.to_dot_to("bright white"),
+            Some("digraph bags {\n    \"light red\";\n    \"bright white\";\n    \"light red\" -> \"bright white\" [label=\"1\"];\n}\n".to_string())
+        );
+        assert_eq!(
+            graph.to_dot_from("muted yellow"),
+            Some("digraph bags {\n    \"muted yellow\";\n    \"shiny gold\";\n    \"faded blue\";\n    \"muted yellow\" -> \"shiny gold\" [label=\"2\"];\n    \"muted yellow\" -> \"faded blue\" [label=\"9\"];\n}\n".to_string())
+        );
+        assert_eq!(graph.to_dot().lines().count(), 12);
+        assert_eq!(graph.to_dot_from("neon pink"), None);
+    }
 }
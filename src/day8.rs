@@ -1,6 +1,7 @@
-use std::collections::HashSet;
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
 use std::fs::File;
-use std::io::Read;
+use std::io::{self, BufRead, Read, Write};
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum Opcode {
@@ -9,104 +10,651 @@ pub enum Opcode {
     Nop,
 }
 
+impl Opcode {
+    // the opcode a single corrupted instruction may have been swapped with
+    pub fn flipped(&self) -> Option<Opcode> {
+        match self {
+            Opcode::Jmp => Some(Opcode::Nop),
+            Opcode::Nop => Some(Opcode::Jmp),
+            Opcode::Acc => None,
+        }
+    }
+}
+
+impl fmt::Display for Opcode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Opcode::Jmp => write!(f, "jmp"),
+            Opcode::Acc => write!(f, "acc"),
+            Opcode::Nop => write!(f, "nop"),
+        }
+    }
+}
+
 #[derive(PartialEq, Debug, Clone)]
 pub struct Instruction {
     op: Opcode,
     param: i32,
 }
 
-pub fn solve_part1(instructions: &[Instruction]) -> i32 {
-    let mut pc = 0;
-    let mut acc = 0;
-    let mut already_viewed_instructions: HashSet<i32> = HashSet::new();
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {:+}", self.op, self.param)
+    }
+}
+
+impl Instruction {
+    pub fn new(op: Opcode, param: i32) -> Instruction {
+        Instruction { op, param }
+    }
+
+    pub fn op(&self) -> &Opcode {
+        &self.op
+    }
 
-    while !already_viewed_instructions.contains(&pc) {
-        already_viewed_instructions.insert(pc);
-        let current_instr = &instructions[pc as usize];
+    pub fn param(&self) -> i32 {
+        self.param
+    }
 
-        pc += 1;
-        match current_instr.op {
-            Opcode::Jmp => pc += current_instr.param - 1,
-            Opcode::Nop => (),
-            Opcode::Acc => acc += current_instr.param,
+    // address executed after this instruction when it sits at `pc`
+    pub fn successor(&self, pc: usize) -> i64 {
+        match self.op {
+            Opcode::Jmp => pc as i64 + self.param as i64,
+            Opcode::Acc | Opcode::Nop => pc as i64 + 1,
         }
     }
+}
 
-    acc
+#[derive(PartialEq, Debug, Clone)]
+pub enum Termination {
+    Halted,
+    InfiniteLoop { pc: usize },
+    OutOfBounds { pc: i64 },
 }
 
-pub fn solve_part2(instructions: &[Instruction]) -> i32 {
-    for (i, instruction) in instructions.iter().enumerate() {
-        match instruction.op {
-            Opcode::Jmp => {
-                let mut instructions = instructions.to_owned();
-                instructions[i].op = Opcode::Nop;
-                if let Some(acc) = run_part2(&instructions) {
-                    println!("instruction was a JMP at {}", i);
-                    return acc;
+#[derive(PartialEq, Debug, Clone)]
+pub struct Machine {
+    program: Vec<Instruction>,
+    pc: i64,
+    acc: i32,
+    visited: Vec<bool>,
+    trace: Vec<usize>, // addresses of the executed instructions, in order
+}
+
+impl Machine {
+    pub fn new(program: &[Instruction]) -> Machine {
+        Machine {
+            program: program.to_owned(),
+            pc: 0,
+            acc: 0,
+            visited: vec![false; program.len()],
+            trace: Vec::new(),
+        }
+    }
+
+    pub fn pc(&self) -> i64 {
+        self.pc
+    }
+
+    pub fn acc(&self) -> i32 {
+        self.acc
+    }
+
+    pub fn trace(&self) -> &[usize] {
+        &self.trace
+    }
+
+    pub fn program(&self) -> &[Instruction] {
+        &self.program
+    }
+
+    // executes the instruction at `pc`, or tells why the machine can't go on
+    pub fn step(&mut self) -> Option<Termination> {
+        // only landing right after the last instruction counts as a normal exit
+        if self.pc == self.program.len() as i64 {
+            return Some(Termination::Halted);
+        }
+        if self.pc < 0 || self.pc > self.program.len() as i64 {
+            return Some(Termination::OutOfBounds { pc: self.pc });
+        }
+        let pc = self.pc as usize;
+        if self.visited[pc] {
+            return Some(Termination::InfiniteLoop { pc });
+        }
+
+        self.visited[pc] = true;
+        self.trace.push(pc);
+        let instruction = &self.program[pc];
+        if instruction.op == Opcode::Acc {
+            // a 32 bits register: wraps around rather than panicking
+            self.acc = self.acc.wrapping_add(instruction.param);
+        }
+        self.pc = instruction.successor(pc);
+        None
+    }
+
+    pub fn run(&mut self) -> Termination {
+        loop {
+            if let Some(termination) = self.step() {
+                return termination;
+            }
+        }
+    }
+
+    // the instruction `step` would execute next
+    pub fn current(&self) -> Option<&Instruction> {
+        if self.pc < 0 {
+            return None;
+        }
+        self.program.get(self.pc as usize)
+    }
+
+    // once `pc` is back on an already executed instruction: the addresses of the loop
+    pub fn loop_cycle(&self) -> Option<&[usize]> {
+        let pc = self.pc as usize;
+        if self.pc < 0 || pc >= self.program.len() || !self.visited[pc] {
+            return None;
+        }
+        let start = self.trace.iter().position(|address| *address == pc)?;
+        Some(&self.trace[start..])
+    }
+}
+
+pub fn solve_part1(instructions: &[Instruction]) -> i32 {
+    let mut machine = Machine::new(instructions);
+    machine.run();
+    machine.acc()
+}
+
+// every instruction has a single successor, the address right after the
+// program (`exit`) being where it halts
+#[derive(PartialEq, Debug, Clone)]
+pub struct ControlFlowGraph {
+    program: Vec<Instruction>,
+    predecessors: Vec<Vec<usize>>, // indexed by address, `exit` included
+    path: Vec<usize>,              // addresses executed from 0 until the program stops
+    exit_tree: Vec<Option<(usize, usize)>>, // pre/post order in the tree of halting addresses
+}
+
+impl ControlFlowGraph {
+    pub fn new(program: &[Instruction]) -> ControlFlowGraph {
+        let len = program.len();
+        let in_program = |address: i64| address >= 0 && address <= len as i64;
+
+        let mut predecessors = vec![Vec::new(); len + 1];
+        for (pc, instruction) in program.iter().enumerate() {
+            let successor = instruction.successor(pc);
+            if in_program(successor) {
+                predecessors[successor as usize].push(pc);
+            }
+        }
+
+        // follow the successors from 0 until leaving the program or coming back
+        let mut path = Vec::new();
+        let mut visited = vec![false; len];
+        let mut pc = 0;
+        while pc >= 0 && pc < len as i64 && !visited[pc as usize] {
+            visited[pc as usize] = true;
+            path.push(pc as usize);
+            pc = program[pc as usize].successor(pc as usize);
+        }
+
+        // walking backward from `exit` only reaches the addresses that halt
+        let mut exit_tree = vec![None; len + 1];
+        let mut order = 0;
+        let mut stack = vec![(len, 0)];
+        exit_tree[len] = Some((order, 0));
+        while let Some(&(address, next)) = stack.last() {
+            match predecessors[address].get(next) {
+                Some(predecessor) => {
+                    stack.last_mut().unwrap().1 += 1;
+                    order += 1;
+                    exit_tree[*predecessor] = Some((order, 0));
+                    stack.push((*predecessor, 0));
+                }
+                None => {
+                    exit_tree[address] = exit_tree[address].map(|(pre, _)| (pre, order));
+                    stack.pop();
                 }
             }
-            Opcode::Nop => {
-                let mut instructions = instructions.to_owned();
-                instructions[i].op = Opcode::Jmp;
-                if let Some(acc) = run_part2(&instructions) {
-                    println!("instruction was a NOP at {}", i);
-                    return acc;
+        }
+
+        ControlFlowGraph {
+            program: program.to_owned(),
+            predecessors,
+            path,
+            exit_tree,
+        }
+    }
+
+    pub fn exit(&self) -> usize {
+        self.program.len()
+    }
+
+    pub fn successor(&self, pc: usize) -> i64 {
+        self.program[pc].successor(pc)
+    }
+
+    pub fn predecessors(&self, address: usize) -> &[usize] {
+        &self.predecessors[address]
+    }
+
+    pub fn execution_path(&self) -> &[usize] {
+        &self.path
+    }
+
+    pub fn halts(&self, address: usize) -> bool {
+        self.exit_tree[address].is_some()
+    }
+
+    // whether `address` goes through `through` on its way to `exit`
+    fn halts_through(&self, address: usize, through: usize) -> bool {
+        match (self.exit_tree[address], self.exit_tree[through]) {
+            (Some((pre, _)), Some((through_pre, through_post))) => {
+                through_pre <= pre && pre <= through_post
+            }
+            _ => false,
+        }
+    }
+
+    pub fn unreachable(&self) -> Vec<usize> {
+        let mut reachable = vec![false; self.program.len()];
+        self.path.iter().for_each(|pc| reachable[*pc] = true);
+        (0..self.program.len())
+            .filter(|pc| !reachable[*pc])
+            .collect()
+    }
+
+    pub fn loops(&self) -> Vec<Vec<usize>> {
+        let len = self.program.len();
+        let mut walked_by = vec![None; len];
+        let mut loops = Vec::new();
+
+        for start in 0..len {
+            let mut walk = Vec::new();
+            let mut pc = start as i64;
+            while pc >= 0 && pc < len as i64 {
+                let address = pc as usize;
+                match walked_by[address] {
+                    Some(walk_start) if walk_start == start => {
+                        let position = walk.iter().position(|pc| *pc == address).unwrap();
+                        loops.push(walk[position..].to_vec());
+                        break;
+                    }
+                    Some(_) => break,
+                    None => (),
                 }
+                walked_by[address] = Some(start);
+                walk.push(address);
+                pc = self.successor(address);
             }
-            _ => (),
         }
+        loops
+    }
+
+    // jmp/nop on the execution path that make the program halt once flipped:
+    // the flipped successor must halt without coming back through the flip
+    pub fn terminating_flips(&self) -> Vec<usize> {
+        self.path
+            .iter()
+            .copied()
+            .filter(|pc| {
+                let instruction = &self.program[*pc];
+                let flipped = match instruction.op.flipped() {
+                    Some(op) => Instruction::new(op, instruction.param),
+                    None => return false,
+                };
+                let successor = flipped.successor(*pc);
+                successor >= 0
+                    && successor <= self.exit() as i64
+                    && self.halts(successor as usize)
+                    && !self.halts_through(successor as usize, *pc)
+            })
+            .collect()
+    }
+
+    pub fn to_text(&self) -> String {
+        let join = |addresses: &[usize], separator: &str| {
+            addresses
+                .iter()
+                .map(|pc| pc.to_string())
+                .collect::<Vec<_>>()
+                .join(separator)
+        };
+        let mut text = format!("instructions: {}\n", self.program.len());
+        text += &format!("halts: {}\n", self.halts(0) || self.program.is_empty());
+        text += &format!("unreachable: {}\n", join(&self.unreachable(), ", "));
+        for cycle in self.loops() {
+            text += &format!("loop: {}\n", join(&cycle, " -> "));
+        }
+        for pc in self.terminating_flips() {
+            let op = &self.program[pc].op;
+            text += &format!(
+                "terminating flip: {} ({} -> {})\n",
+                pc,
+                op,
+                op.flipped().unwrap()
+            );
+        }
+        text
     }
 
-    unreachable!();
+    // unreachable instructions are dashed, instructions in a loop are red
+    pub fn to_dot(&self) -> String {
+        let mut unreachable = vec![false; self.program.len()];
+        self.unreachable()
+            .into_iter()
+            .for_each(|pc| unreachable[pc] = true);
+        let mut in_loop = vec![false; self.program.len()];
+        self.loops()
+            .into_iter()
+            .flatten()
+            .for_each(|pc| in_loop[pc] = true);
+        let mut dot = String::from("digraph program {\n");
+        for (pc, instruction) in self.program.iter().enumerate() {
+            let mut style = String::new();
+            if unreachable[pc] {
+                style += ", style=dashed";
+            }
+            if in_loop[pc] {
+                style += ", color=red";
+            }
+            dot += &format!("    {} [label=\"{}: {}\"{}];\n", pc, pc, instruction, style);
+        }
+        dot += &format!(
+            "    {} [label=\"exit\", shape=doublecircle];\n",
+            self.exit()
+        );
+        for pc in 0..self.program.len() {
+            let successor = self.successor(pc);
+            if successor >= 0 && successor <= self.exit() as i64 {
+                dot += &format!("    {} -> {};\n", pc, successor);
+            }
+        }
+        for pc in self.terminating_flips() {
+            let op = self.program[pc].op.flipped().unwrap();
+            let successor = Instruction::new(op, self.program[pc].param).successor(pc);
+            dot += &format!(
+                "    {} -> {} [style=dotted, color=green, label=\"flip\"];\n",
+                pc, successor
+            );
+        }
+        dot += "}\n";
+        dot
+    }
 }
 
-fn run_part2(instructions: &[Instruction]) -> Option<i32> {
-    let mut pc = 0;
-    let mut acc = 0;
-    let mut already_viewed_instructions: HashSet<i32> = HashSet::new();
+#[derive(PartialEq, Debug, Clone)]
+pub struct Repair {
+    pub index: usize,
+    pub original: Opcode,
+    pub acc: i32,
+}
 
-    loop {
-        if already_viewed_instructions.contains(&pc) {
-            return None;
+// the first flip on the execution path that makes the program halt
+pub fn repair(program: &[Instruction]) -> Option<Repair> {
+    let index = *ControlFlowGraph::new(program).terminating_flips().first()?;
+    let mut program = program.to_owned();
+    let original = program[index].op.clone();
+    program[index].op = original.flipped().unwrap();
+    let mut machine = Machine::new(&program);
+    // `terminating_flips` only keeps flips that can't come back through themselves
+    let termination = machine.run();
+    debug_assert_eq!(termination, Termination::Halted);
+    Some(Repair {
+        index,
+        original,
+        acc: machine.acc(),
+    })
+}
+
+pub fn solve_part2(instructions: &[Instruction]) -> i32 {
+    repair(instructions).unwrap().acc
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum AsmError {
+    UnknownOpcode { line: usize, opcode: String },
+    InvalidParam { line: usize, param: String },
+    InvalidLabel { line: usize, label: String },
+    DuplicateLabel { line: usize, label: String },
+    UndefinedLabel { line: usize, label: String },
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AsmError::UnknownOpcode { line, opcode } => {
+                write!(f, "line {}: unknown opcode `{}`", line, opcode)
+            }
+            AsmError::InvalidParam { line, param } => {
+                write!(f, "line {}: invalid parameter `{}`", line, param)
+            }
+            AsmError::InvalidLabel { line, label } => {
+                write!(f, "line {}: invalid label `{}`", line, label)
+            }
+            AsmError::DuplicateLabel { line, label } => {
+                write!(f, "line {}: label `{}` is already defined", line, label)
+            }
+            AsmError::UndefinedLabel { line, label } => {
+                write!(f, "line {}: label `{}` is not defined", line, label)
+            }
         }
+    }
+}
+
+fn is_label(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+// the puzzle syntax (`jmp +4`), plus `#` comments, blank lines and
+// `label:` definitions that `jmp`/`nop` can use instead of an offset
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut statements = Vec::new();
 
-        already_viewed_instructions.insert(pc);
-        let current_instr = &instructions[pc as usize];
+    for (line_number, line) in source.lines().enumerate() {
+        let line_number = line_number + 1;
+        let mut code = line.split('#').next().unwrap().trim();
 
-        pc += 1;
-        match current_instr.op {
-            Opcode::Jmp => pc += current_instr.param - 1,
-            Opcode::Nop => (),
-            Opcode::Acc => acc += current_instr.param,
+        while let Some(colon) = code.find(':') {
+            let label = code[..colon].trim();
+            if !is_label(label) {
+                return Err(AsmError::InvalidLabel {
+                    line: line_number,
+                    label: label.to_owned(),
+                });
+            }
+            if labels.insert(label, statements.len()).is_some() {
+                return Err(AsmError::DuplicateLabel {
+                    line: line_number,
+                    label: label.to_owned(),
+                });
+            }
+            code = code[colon + 1..].trim();
         }
 
-        if pc as usize >= instructions.len() {
-            return Some(acc);
+        if !code.is_empty() {
+            statements.push((line_number, code));
         }
     }
+
+    statements
+        .iter()
+        .enumerate()
+        .map(|(address, (line, code))| {
+            let mut words = code.split_whitespace();
+            let opcode = words.next().unwrap();
+            let op = match opcode {
+                "jmp" => Opcode::Jmp,
+                "acc" => Opcode::Acc,
+                "nop" => Opcode::Nop,
+                _ => {
+                    return Err(AsmError::UnknownOpcode {
+                        line: *line,
+                        opcode: opcode.to_owned(),
+                    })
+                }
+            };
+            let param = match (words.next(), words.next()) {
+                (Some(param), None) => param,
+                _ => {
+                    return Err(AsmError::InvalidParam {
+                        line: *line,
+                        param: code[opcode.len()..].trim().to_owned(),
+                    })
+                }
+            };
+            let param = if let Ok(param) = param.parse() {
+                param
+            } else if op != Opcode::Acc && is_label(param) {
+                match labels.get(param) {
+                    Some(target) => *target as i32 - address as i32,
+                    None => {
+                        return Err(AsmError::UndefinedLabel {
+                            line: *line,
+                            label: param.to_owned(),
+                        })
+                    }
+                }
+            } else {
+                return Err(AsmError::InvalidParam {
+                    line: *line,
+                    param: param.to_owned(),
+                });
+            };
+            Ok(Instruction { op, param })
+        })
+        .collect()
 }
 
-pub fn parse_part1(input: &str) -> Vec<Instruction> {
-    input
-        .lines()
-        .map(
-            |line| match line.split(' ').collect::<Vec<_>>().as_slice() {
-                [op, param] => Instruction {
-                    op: match *op {
-                        "jmp" => Opcode::Jmp,
-                        "acc" => Opcode::Acc,
-                        "nop" => Opcode::Nop,
-                        _ => panic!(),
-                    },
-                    param: param.parse().unwrap(),
-                },
-                _ => panic!(),
-            },
-        )
+// the puzzle syntax, one instruction per line
+pub fn disassemble(program: &[Instruction]) -> String {
+    program
+        .iter()
+        .map(|instruction| format!("{}\n", instruction))
         .collect()
 }
 
+// a listing with addresses, where every jump target in the program gets a label;
+// the output assembles back to the same program
+pub fn pretty_print(program: &[Instruction]) -> String {
+    let in_program = |address: i64| address >= 0 && address <= program.len() as i64;
+    let mut targets = vec![false; program.len() + 1];
+    for (pc, instruction) in program.iter().enumerate() {
+        let target = pc as i64 + instruction.param as i64;
+        if instruction.op == Opcode::Jmp && in_program(target) {
+            targets[target as usize] = true;
+        }
+    }
+
+    let mut listing = String::new();
+    for (pc, instruction) in program.iter().enumerate() {
+        if targets[pc] {
+            listing += &format!("l{}:\n", pc);
+        }
+        let target = pc as i64 + instruction.param as i64;
+        let line = match instruction.op {
+            Opcode::Jmp if in_program(target) => format!("jmp l{}", target),
+            _ => instruction.to_string(),
+        };
+        let comment = match instruction.op {
+            Opcode::Jmp => format!("{} -> {}", pc, target),
+            Opcode::Nop => format!("{} (-> {} as jmp)", pc, target),
+            Opcode::Acc => pc.to_string(),
+        };
+        listing += &format!("    {:<12}# {}\n", line, comment);
+    }
+    if targets[program.len()] {
+        listing += &format!("l{}:\n", program.len());
+    }
+    listing
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum DebugEvent {
+    Stepped { pc: usize },
+    Breakpoint { pc: usize },
+    AccChanged { pc: usize, old: i32, new: i32 }, // `pc` is the address of the acc instruction
+    Terminated(Termination),
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Debugger {
+    machine: Machine,
+    breakpoints: BTreeSet<usize>,
+    watch_acc: bool,
+}
+
+impl Debugger {
+    pub fn new(program: &[Instruction]) -> Debugger {
+        Debugger {
+            machine: Machine::new(program),
+            breakpoints: BTreeSet::new(),
+            watch_acc: false,
+        }
+    }
+
+    pub fn machine(&self) -> &Machine {
+        &self.machine
+    }
+
+    pub fn breakpoints(&self) -> &BTreeSet<usize> {
+        &self.breakpoints
+    }
+
+    pub fn add_breakpoint(&mut self, pc: usize) -> bool {
+        self.breakpoints.insert(pc)
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: usize) -> bool {
+        self.breakpoints.remove(&pc)
+    }
+
+    pub fn watch_acc(&mut self, watch: bool) {
+        self.watch_acc = watch;
+    }
+
+    pub fn is_watching_acc(&self) -> bool {
+        self.watch_acc
+    }
+
+    pub fn step(&mut self) -> DebugEvent {
+        let pc = self.machine.pc();
+        let old = self.machine.acc();
+        if let Some(termination) = self.machine.step() {
+            return DebugEvent::Terminated(termination);
+        }
+        let pc = pc as usize;
+        let new = self.machine.acc();
+        if self.watch_acc && old != new {
+            DebugEvent::AccChanged { pc, old, new }
+        } else {
+            DebugEvent::Stepped { pc }
+        }
+    }
+
+    // runs until a breakpoint is reached, the watched acc changes or the machine stops;
+    // a breakpoint on the current instruction doesn't stop it from being executed
+    pub fn resume(&mut self) -> DebugEvent {
+        loop {
+            match self.step() {
+                DebugEvent::Stepped { .. } => (),
+                event => return event,
+            }
+            let pc = self.machine.pc();
+            if pc >= 0 && self.breakpoints.contains(&(pc as usize)) {
+                return DebugEvent::Breakpoint { pc: pc as usize };
+            }
+        }
+    }
+}
+
+pub fn parse_part1(input: &str) -> Vec<Instruction> {
+    assemble(input).unwrap()
+}
+
 pub fn part1() {
     let mut file = File::open("input/2020/day8.txt").unwrap();
     let mut input = String::new();
@@ -121,6 +669,130 @@ pub fn part2() {
     println!("{}", solve_part2(&parse_part1(&input)));
 }
 
+// a line based debugger session over `input`, reporting on `output`
+pub fn debug_session<R: BufRead, W: Write>(
+    program: &[Instruction],
+    input: R,
+    mut output: W,
+) -> io::Result<()> {
+    let mut debugger = Debugger::new(program);
+
+    for line in input.lines() {
+        let line = line?;
+        let event = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+            ["s"] | ["step"] => Some(debugger.step()),
+            ["c"] | ["continue"] => Some(debugger.resume()),
+            ["b", pc] | ["break", pc] => {
+                match pc.parse() {
+                    Ok(pc) => {
+                        debugger.add_breakpoint(pc);
+                        writeln!(output, "breakpoint at {}", pc)?;
+                    }
+                    Err(_) => writeln!(output, "invalid address: {}", pc)?,
+                }
+                None
+            }
+            ["d", pc] | ["delete", pc] => {
+                match pc.parse() {
+                    Ok(pc) if debugger.remove_breakpoint(pc) => {
+                        writeln!(output, "deleted breakpoint at {}", pc)?
+                    }
+                    _ => writeln!(output, "no breakpoint at {}", pc)?,
+                }
+                None
+            }
+            ["w"] | ["watch"] => {
+                let watch = !debugger.is_watching_acc();
+                debugger.watch_acc(watch);
+                writeln!(output, "watching acc: {}", watch)?;
+                None
+            }
+            ["p"] | ["print"] => {
+                let machine = debugger.machine();
+                match machine.current() {
+                    Some(instruction) => writeln!(
+                        output,
+                        "pc={} acc={} next={}",
+                        machine.pc(),
+                        machine.acc(),
+                        instruction
+                    )?,
+                    None => writeln!(output, "pc={} acc={}", machine.pc(), machine.acc())?,
+                }
+                None
+            }
+            ["l"] | ["list"] => {
+                write!(output, "{}", pretty_print(debugger.machine().program()))?;
+                None
+            }
+            ["q"] | ["quit"] => break,
+            [] => None,
+            _ => {
+                writeln!(
+                    output,
+                    "commands: step, continue, break <pc>, delete <pc>, watch, print, list, quit"
+                )?;
+                None
+            }
+        };
+
+        match event {
+            Some(DebugEvent::Stepped { pc }) => writeln!(output, "executed {}", pc)?,
+            Some(DebugEvent::Breakpoint { pc }) => writeln!(output, "breakpoint hit at {}", pc)?,
+            Some(DebugEvent::AccChanged { pc, old, new }) => {
+                writeln!(output, "acc changed at {}: {} -> {}", pc, old, new)?
+            }
+            Some(DebugEvent::Terminated(Termination::InfiniteLoop { pc })) => {
+                let cycle = debugger.machine().loop_cycle().unwrap_or(&[]);
+                writeln!(
+                    output,
+                    "infinite loop at {} with acc={}, cycle: {}",
+                    pc,
+                    debugger.machine().acc(),
+                    cycle
+                        .iter()
+                        .map(|pc| pc.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" -> ")
+                )?
+            }
+            Some(DebugEvent::Terminated(termination)) => writeln!(
+                output,
+                "{:?} with acc={}",
+                termination,
+                debugger.machine().acc()
+            )?,
+            None => (),
+        }
+    }
+
+    Ok(())
+}
+
+pub fn debug(path: &str) {
+    let mut file = File::open(path).unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+    match assemble(&input) {
+        Ok(program) => {
+            let stdin = io::stdin();
+            debug_session(&program, stdin.lock(), io::stdout()).unwrap();
+        }
+        Err(error) => eprintln!("{}", error),
+    }
+}
+
+pub fn analyze(path: &str, as_dot: bool) {
+    let mut file = File::open(path).unwrap();
+    let mut input = String::new();
+    file.read_to_string(&mut input).unwrap();
+    match assemble(&input) {
+        Ok(program) if as_dot => print!("{}", ControlFlowGraph::new(&program).to_dot()),
+        Ok(program) => print!("{}", ControlFlowGraph::new(&program).to_text()),
+        Err(error) => eprintln!("{}", error),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -179,4 +851,288 @@ mod test {
             ]
         )
     }
+
+    #[test]
+    fn solve_part2_example() {
+        assert_eq!(
+            solve_part2(&parse_part1(
+                "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6"
+            )),
+            8
+        );
+    }
+
+    #[test]
+    fn test_machine_run() {
+        let mut machine = Machine::new(&parse_part1(
+            "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6",
+        ));
+        assert_eq!(machine.run(), Termination::InfiniteLoop { pc: 1 });
+        assert_eq!(machine.acc(), 5);
+        assert_eq!(machine.trace(), &[0, 1, 2, 6, 7, 3, 4]);
+
+        let mut machine = Machine::new(&parse_part1("acc +2\nnop +0\nacc -5"));
+        assert_eq!(machine.step(), None);
+        assert_eq!((machine.pc(), machine.acc()), (1, 2));
+        assert_eq!(machine.run(), Termination::Halted);
+        assert_eq!((machine.pc(), machine.acc()), (3, -3));
+    }
+
+    #[test]
+    fn test_machine_acc_wraps_around() {
+        let mut machine = Machine::new(&parse_part1("acc +2147483647\nacc +1"));
+        assert_eq!(machine.run(), Termination::Halted);
+        assert_eq!(machine.acc(), i32::MIN);
+    }
+
+    #[test]
+    fn test_machine_jump_before_start() {
+        let mut machine = Machine::new(&parse_part1("acc +1\njmp -2\nacc +1"));
+        assert_eq!(machine.run(), Termination::OutOfBounds { pc: -1 });
+        assert_eq!(machine.acc(), 1);
+        assert_eq!(machine.trace(), &[0, 1]);
+    }
+
+    #[test]
+    fn test_machine_jump_past_end() {
+        let mut machine = Machine::new(&parse_part1("acc +1\njmp +3\nacc +1"));
+        assert_eq!(machine.run(), Termination::OutOfBounds { pc: 4 });
+        assert_eq!(machine.acc(), 1);
+
+        let mut machine = Machine::new(&parse_part1("acc +1\njmp +2\nacc +1"));
+        assert_eq!(machine.run(), Termination::Halted);
+        assert_eq!(machine.pc(), 3);
+    }
+
+    #[test]
+    fn test_machine_out_of_bounds_is_not_a_repair() {
+        assert_eq!(
+            repair(&parse_part1("nop +5\nacc +1\njmp -1")),
+            Some(Repair {
+                index: 2,
+                original: Opcode::Jmp,
+                acc: 1,
+            })
+        );
+        assert_eq!(repair(&parse_part1("acc +3\njmp -2\njmp -1")), None);
+    }
+
+    #[test]
+    fn test_repair_example() {
+        assert_eq!(
+            repair(&parse_part1(
+                "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6"
+            )),
+            Some(Repair {
+                index: 7,
+                original: Opcode::Jmp,
+                acc: 8,
+            })
+        );
+        assert_eq!(
+            repair(&parse_part1("nop +3\nacc +1\njmp -1")),
+            Some(Repair {
+                index: 0,
+                original: Opcode::Nop,
+                acc: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_repair_does_not_flip_back_into_itself() {
+        // flipping 0 to `jmp +0` lands on 0, which only halts through the flip
+        assert_eq!(
+            repair(&parse_part1("nop +0\nnop -1\njmp +1")),
+            Some(Repair {
+                index: 2,
+                original: Opcode::Jmp,
+                acc: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn test_assemble_labels_and_comments() {
+        assert_eq!(
+            assemble("# count down\nstart: acc +1\n\nnop end # skipped\nloop:\n  jmp start\nend:"),
+            Ok(vec![
+                Instruction::new(Opcode::Acc, 1),
+                Instruction::new(Opcode::Nop, 2),
+                Instruction::new(Opcode::Jmp, -2),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        assert_eq!(
+            assemble("acc +1\nmul +2"),
+            Err(AsmError::UnknownOpcode {
+                line: 2,
+                opcode: "mul".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("acc end\nend:"),
+            Err(AsmError::InvalidParam {
+                line: 1,
+                param: "end".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("nop +1 +2"),
+            Err(AsmError::InvalidParam {
+                line: 1,
+                param: "+1 +2".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("jmp nowhere"),
+            Err(AsmError::UndefinedLabel {
+                line: 1,
+                label: "nowhere".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("a: nop +0\na: nop +0"),
+            Err(AsmError::DuplicateLabel {
+                line: 2,
+                label: "a".to_string()
+            })
+        );
+        assert_eq!(
+            assemble("1a: nop +0"),
+            Err(AsmError::InvalidLabel {
+                line: 1,
+                label: "1a".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_disassemble_round_trip() {
+        let source = "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6\n";
+        let program = parse_part1(source);
+        assert_eq!(disassemble(&program), source);
+        assert_eq!(assemble(&pretty_print(&program)), Ok(program));
+    }
+
+    #[test]
+    fn test_pretty_print() {
+        assert_eq!(
+            pretty_print(&parse_part1("nop +2\nacc -1\njmp -2\njmp +1\njmp -9")),
+            "l0:\n    nop +2      # 0 (-> 2 as jmp)\n    acc -1      # 1\n    jmp l0      # 2 -> 0\n    jmp l4      # 3 -> 4\nl4:\n    jmp -9      # 4 -> -5\n"
+        );
+    }
+
+    #[test]
+    fn test_machine_loop_cycle() {
+        let mut machine = Machine::new(&parse_part1(
+            "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6",
+        ));
+        assert_eq!(machine.loop_cycle(), None);
+        machine.run();
+        assert_eq!(machine.loop_cycle(), Some(&[1, 2, 6, 7, 3, 4][..]));
+        assert_eq!(machine.current(), Some(&Instruction::new(Opcode::Acc, 1)));
+    }
+
+    #[test]
+    fn test_debugger() {
+        let mut debugger = Debugger::new(&parse_part1(
+            "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6",
+        ));
+        assert!(debugger.add_breakpoint(6));
+        assert_eq!(debugger.step(), DebugEvent::Stepped { pc: 0 });
+        assert_eq!(debugger.resume(), DebugEvent::Breakpoint { pc: 6 });
+        assert_eq!(debugger.machine().acc(), 1);
+        debugger.watch_acc(true);
+        assert_eq!(
+            debugger.resume(),
+            DebugEvent::AccChanged {
+                pc: 6,
+                old: 1,
+                new: 2
+            }
+        );
+        assert_eq!(
+            debugger.resume(),
+            DebugEvent::AccChanged {
+                pc: 3,
+                old: 2,
+                new: 5
+            }
+        );
+        assert!(debugger.remove_breakpoint(6));
+        assert_eq!(
+            debugger.resume(),
+            DebugEvent::Terminated(Termination::InfiniteLoop { pc: 1 })
+        );
+    }
+
+    #[test]
+    fn test_debug_session() {
+        let program = parse_part1("acc +2\njmp +1\njmp -1");
+        let mut output = Vec::new();
+        debug_session(
+            &program,
+            "b 2\np\nc\nw\ns\nc\nq\nc\n".as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "breakpoint at 2\npc=0 acc=0 next=acc +2\nbreakpoint hit at 2\nwatching acc: true\nexecuted 2\ninfinite loop at 1 with acc=2, cycle: 1 -> 2\n"
+        );
+    }
+
+    #[test]
+    fn test_control_flow_graph_example() {
+        let cfg = ControlFlowGraph::new(&parse_part1(
+            "nop +0\nacc +1\njmp +4\nacc +3\njmp -3\nacc -99\nacc +1\njmp -4\nacc +6",
+        ));
+        assert_eq!(cfg.execution_path(), &[0, 1, 2, 6, 7, 3, 4]);
+        assert_eq!(cfg.predecessors(6), &[2, 5]);
+        assert_eq!(cfg.unreachable(), vec![5, 8]);
+        assert_eq!(cfg.loops(), vec![vec![1, 2, 6, 7, 3, 4]]);
+        assert_eq!(cfg.terminating_flips(), vec![7]);
+        assert!(cfg.halts(8));
+        assert!(!cfg.halts(0));
+        assert_eq!(
+            cfg.to_text(),
+            "instructions: 9\nhalts: false\nunreachable: 5, 8\nloop: 1 -> 2 -> 6 -> 7 -> 3 -> 4\nterminating flip: 7 (jmp -> nop)\n"
+        );
+    }
+
+    #[test]
+    fn test_control_flow_graph_flip_back_into_itself() {
+        // flipping 0 or 1 lands on 0 which halts, but only by going back through the flip
+        let cfg = ControlFlowGraph::new(&parse_part1("nop +0\nnop -1\njmp +1"));
+        assert!(cfg.halts(0));
+        assert_eq!(cfg.terminating_flips(), vec![2]);
+        let cfg = ControlFlowGraph::new(&parse_part1("jmp +2\njmp +2\njmp -1"));
+        assert_eq!(cfg.execution_path(), &[0, 2, 1]);
+        assert!(cfg.halts(1));
+        assert_eq!(cfg.terminating_flips(), vec![0, 2]);
+        assert_eq!(cfg.loops(), Vec::<Vec<usize>>::new());
+    }
+
+    #[test]
+    fn test_control_flow_graph_path_leaving_program() {
+        let cfg = ControlFlowGraph::new(&parse_part1("acc +1\njmp -2\nacc +1"));
+        assert_eq!(cfg.execution_path(), &[0, 1]);
+        assert_eq!(cfg.unreachable(), vec![2]);
+        let cfg = ControlFlowGraph::new(&parse_part1("acc +1\njmp +2\nacc +1"));
+        assert_eq!(cfg.execution_path(), &[0, 1]);
+        assert!(cfg.halts(0));
+    }
+
+    #[test]
+    fn test_control_flow_graph_dot() {
+        let dot = ControlFlowGraph::new(&parse_part1("nop +0\njmp -1\nacc +1")).to_dot();
+        assert_eq!(
+            dot,
+            "digraph program {\n    0 [label=\"0: nop +0\", color=red];\n    1 [label=\"1: jmp -1\", color=red];\n    2 [label=\"2: acc +1\", style=dashed];\n    3 [label=\"exit\", shape=doublecircle];\n    0 -> 1;\n    1 -> 0;\n    2 -> 3;\n    1 -> 2 [style=dotted, color=green, label=\"flip\"];\n}\n"
+        );
+    }
 }
@@ -1,5 +1,6 @@
 use itertools::Itertools;
 use std::cmp::Ordering;
+use std::collections::VecDeque;
 use std::fs::File;
 use std::io::Read;
 
@@ -24,52 +25,175 @@ pub fn solve_part1_windows(input: &[usize]) -> usize {
         .unwrap()[25]
 }
 
+#[derive(PartialEq, Debug, Clone)]
+pub enum XmasError {
+    EmptyPreamble,
+    InputShorterThanPreamble { len: usize, preamble: usize },
+}
+
+// a sliding window of numbers kept sorted as numbers enter and leave it,
+// so that finding a pair adding to a sum is a single O(window) pass
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct PairSums {
+    window: VecDeque<usize>,
+    sorted: Vec<usize>,
+}
+
+impl PairSums {
+    pub fn new(numbers: &[usize]) -> PairSums {
+        let mut pair_sums = PairSums::default();
+        numbers.iter().for_each(|number| pair_sums.push(*number));
+        pair_sums
+    }
+
+    pub fn push(&mut self, number: usize) {
+        let position = match self.sorted.binary_search(&number) {
+            Ok(position) | Err(position) => position,
+        };
+        self.sorted.insert(position, number);
+        self.window.push_back(number);
+    }
+
+    pub fn pop_front(&mut self) -> Option<usize> {
+        let number = self.window.pop_front()?;
+        let position = self.sorted.binary_search(&number).unwrap();
+        self.sorted.remove(position);
+        Some(number)
+    }
+
+    pub fn slide(&mut self, number: usize) {
+        self.pop_front();
+        self.push(number);
+    }
+
+    // whether two numbers with different values in the window add to `sum`
+    pub fn contains(&self, sum: usize) -> bool {
+        if self.sorted.is_empty() {
+            return false;
+        }
+        let (mut low, mut high) = (0, self.sorted.len() - 1);
+        while low < high {
+            // a pair too large for usize is larger than any sum
+            let pair_sum = self.sorted[low].checked_add(self.sorted[high]);
+            match pair_sum.map_or(Ordering::Greater, |pair_sum| pair_sum.cmp(&sum)) {
+                // everything in between has the same value as both ends
+                Ordering::Equal => return self.sorted[low] != self.sorted[high],
+                Ordering::Less => low += 1,
+                Ordering::Greater => high -= 1,
+            }
+        }
+        false
+    }
+}
+
+// (index, number) of every number that isn't the sum of two different numbers
+// among the `preamble` numbers before it
+pub fn find_invalid_numbers(
+    input: &[usize],
+    preamble: usize,
+) -> Result<Vec<(usize, usize)>, XmasError> {
+    if preamble == 0 {
+        return Err(XmasError::EmptyPreamble);
+    }
+    if input.len() < preamble {
+        return Err(XmasError::InputShorterThanPreamble {
+            len: input.len(),
+            preamble,
+        });
+    }
+
+    let mut pair_sums = PairSums::new(&input[..preamble]);
+    let mut invalid_numbers = Vec::new();
+    for (i, number) in input.iter().enumerate().skip(preamble) {
+        if !pair_sums.contains(*number) {
+            invalid_numbers.push((i, *number));
+        }
+        pair_sums.slide(*number);
+    }
+    Ok(invalid_numbers)
+}
+
 pub fn crack_xmas(input: &[usize], preamble: usize) -> Option<usize> {
-    for (i, _) in input.iter().enumerate() {
-        // will panic if input[i+preamble] is OOB
-        if found_vulnerability(&input[i..i + preamble], input[i + preamble]) {
-            return Some(input[i + preamble]);
+    if preamble == 0 || input.len() < preamble {
+        return None;
+    }
+
+    let mut pair_sums = PairSums::new(&input[..preamble]);
+    for number in &input[preamble..] {
+        if !pair_sums.contains(*number) {
+            return Some(*number);
         }
+        pair_sums.slide(*number);
     }
     None
 }
 
-fn found_vulnerability(input: &[usize], must_add_to: usize) -> bool {
-    input
-        .iter()
-        .cartesian_product(input)
-        .find(|(l, r)| *l + *r == must_add_to)
-        .is_none()
+#[derive(PartialEq, Debug, Clone)]
+pub struct ContiguousRange {
+    pub start: usize,
+    pub end: usize, // exclusive
+    pub weakness: usize,
 }
 
-pub fn solve_part2_paul_b(input: &[usize], to_find: usize) -> usize {
+// a run of at least two numbers adding to `target`, found by growing the run
+// on the right while the sum is too small and shrinking it on the left otherwise
+pub fn find_contiguous_range(input: &[usize], target: usize) -> Option<ContiguousRange> {
     let mut lower = 0;
-    let mut upper = 2;
+    let mut upper = 0;
+    let mut sum = 0;
     loop {
-        match input[lower..upper].iter().sum::<usize>().cmp(&to_find) {
-            Ordering::Equal => {
-                return input[lower..upper].iter().max().unwrap()
-                    + input[lower..upper].iter().min().unwrap()
+        let len = upper - lower;
+        if len >= 2 && sum == target {
+            let range = &input[lower..upper];
+            return Some(ContiguousRange {
+                start: lower,
+                end: upper,
+                weakness: range.iter().min().unwrap() + range.iter().max().unwrap(),
+            });
+        }
+        if sum < target || len < 2 {
+            if upper == input.len() {
+                return None;
             }
-            Ordering::Less => upper += 1,
-            Ordering::Greater => lower += 1,
+            match sum.checked_add(input[upper]) {
+                Some(next_sum) => {
+                    sum = next_sum;
+                    upper += 1;
+                }
+                // too large for usize means too large for `target`
+                None => {
+                    sum -= input[lower];
+                    lower += 1;
+                }
+            }
+        } else {
+            sum -= input[lower];
+            lower += 1;
         }
     }
 }
 
-pub fn solve_part2_windows(
-    input: &[usize],
-    invalid_number_index: usize,
-    invalid_number: usize,
-) -> usize {
-    (2..)
-        .find_map(|range_len| {
-            input[..invalid_number_index]
-                .windows(range_len)
-                .find(|range| range.iter().sum::<usize>() == invalid_number)
-                .map(|range| range.iter().min().unwrap() + range.iter().max().unwrap())
-        })
-        .unwrap()
+// the range adding to the first invalid number
+pub fn find_weakness(input: &[usize], preamble: usize) -> Option<ContiguousRange> {
+    find_contiguous_range(input, crack_xmas(input, preamble)?)
+}
+
+pub fn solve_part2_paul_b(input: &[usize], to_find: usize) -> Option<usize> {
+    find_contiguous_range(input, to_find).map(|range| range.weakness)
+}
+
+// same answer as `find_weakness`, trying every window length in turn
+pub fn solve_part2_windows(input: &[usize], preamble: usize) -> Option<usize> {
+    let (invalid_number_index, invalid_number) =
+        *find_invalid_numbers(input, preamble).ok()?.first()?;
+    (2..=invalid_number_index).find_map(|range_len| {
+        input[..invalid_number_index]
+            .windows(range_len)
+            .find(|range| {
+                range.iter().try_fold(0usize, |sum, n| sum.checked_add(*n)) == Some(invalid_number)
+            })
+            .map(|range| range.iter().min().unwrap() + range.iter().max().unwrap())
+    })
 }
 
 pub fn part1() {
@@ -83,7 +207,10 @@ pub fn part2() {
     let mut file = File::open("input/2020/day9.txt").unwrap();
     let mut input = String::new();
     file.read_to_string(&mut input).unwrap();
-    println!("{}", solve_part2_paul_b(&parse_part1(&input), 10884537));
+    println!(
+        "{}",
+        find_weakness(&parse_part1(&input), 25).unwrap().weakness
+    );
 }
 
 #[cfg(test)]
@@ -102,6 +229,113 @@ mod test {
 
     #[test]
     fn solve_part2_paul_b_example() {
-        assert_eq!(solve_part2_paul_b(&parse_part1("35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n219\n299\n277\n309\n576"), 127), 62);
+        assert_eq!(solve_part2_paul_b(&parse_part1("35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n219\n299\n277\n309\n576"), 127), Some(62));
+    }
+
+    #[test]
+    fn solve_part2_windows_example() {
+        assert_eq!(solve_part2_windows(&parse_part1("35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n219\n299\n277\n309\n576"), 5), Some(62));
+    }
+
+    #[test]
+    fn test_solve_part2_without_range() {
+        assert_eq!(solve_part2_paul_b(&[1, 2, 4, 8], 5), None);
+        assert_eq!(solve_part2_windows(&[1, 2, 4, 8, 100], 2), None);
+        assert_eq!(solve_part2_windows(&[1, 2, 3, 5], 2), None);
+    }
+
+    #[test]
+    fn test_find_invalid_numbers() {
+        let input = parse_part1("35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n219\n299\n277\n309\n576");
+        assert_eq!(find_invalid_numbers(&input, 5), Ok(vec![(14, 127)]));
+        assert_eq!(find_invalid_numbers(&input, 20), Ok(vec![]));
+        assert_eq!(
+            find_invalid_numbers(&input, 21),
+            Err(XmasError::InputShorterThanPreamble {
+                len: 20,
+                preamble: 21
+            })
+        );
+        assert_eq!(
+            find_invalid_numbers(&input, 0),
+            Err(XmasError::EmptyPreamble)
+        );
+        assert_eq!(
+            find_invalid_numbers(&[1, 2, 3, 10, 13, 24], 2),
+            Ok(vec![(3, 10), (5, 24)])
+        );
+    }
+
+    #[test]
+    fn test_crack_xmas_without_invalid_number() {
+        assert_eq!(crack_xmas(&[1, 2, 3, 5, 8], 2), None);
+        assert_eq!(crack_xmas(&[1, 2], 5), None);
+    }
+
+    #[test]
+    fn test_pair_sums_sliding() {
+        let mut pair_sums = PairSums::new(&[1, 2, 3]);
+        assert!(pair_sums.contains(3));
+        assert!(pair_sums.contains(5));
+        assert!(!pair_sums.contains(2));
+        pair_sums.slide(10);
+        assert!(!pair_sums.contains(3));
+        assert!(pair_sums.contains(12));
+        assert!(pair_sums.contains(13));
+    }
+
+    #[test]
+    fn test_numbers_are_not_added_to_themselves() {
+        assert_eq!(crack_xmas(&[5, 10, 20], 2), Some(20));
+        assert_eq!(crack_xmas(&[10, 5, 10, 20], 3), Some(20));
+        assert_eq!(crack_xmas(&[10, 10, 5, 15], 3), None);
+    }
+
+    #[test]
+    fn test_pair_sums_overflow() {
+        assert_eq!(crack_xmas(&[usize::MAX, 1, 2], 2), Some(2));
+        assert_eq!(crack_xmas(&[1, usize::MAX - 1, usize::MAX], 2), None);
+    }
+
+    #[test]
+    fn test_find_weakness_example() {
+        assert_eq!(
+            find_weakness(&parse_part1("35\n20\n15\n25\n47\n40\n62\n55\n65\n95\n102\n117\n150\n182\n127\n219\n299\n277\n309\n576"), 5),
+            Some(ContiguousRange {
+                start: 2,
+                end: 6,
+                weakness: 62
+            })
+        );
+    }
+
+    #[test]
+    fn test_find_contiguous_range_without_range() {
+        assert_eq!(find_contiguous_range(&[1, 2, 3], 100), None);
+        assert_eq!(find_contiguous_range(&[10, 1, 2], 10), None);
+        assert_eq!(find_contiguous_range(&[], 0), None);
+        assert_eq!(
+            find_contiguous_range(&[10, 1, 9], 10),
+            Some(ContiguousRange {
+                start: 1,
+                end: 3,
+                weakness: 10
+            })
+        );
+        assert_eq!(find_weakness(&[1, 2, 3, 5, 8], 2), None);
+    }
+
+    #[test]
+    fn test_find_contiguous_range_overflow() {
+        assert_eq!(
+            find_contiguous_range(&[usize::MAX, 1, 2], 3),
+            Some(ContiguousRange {
+                start: 1,
+                end: 3,
+                weakness: 3
+            })
+        );
+        assert_eq!(find_contiguous_range(&[usize::MAX, usize::MAX], 5), None);
+        assert_eq!(find_contiguous_range(&[1, usize::MAX, 2], 3), None);
     }
 }
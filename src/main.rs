@@ -1,3 +1,20 @@
+use aoc_2020::{day11, day12, day6, day7, day8};
+use std::env;
+
 pub fn main() {
-    println!("advent of code 2020")
-}
\ No newline at end of file
+    let args = env::args().skip(1).collect::<Vec<String>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<&str>>();
+    match args.as_slice() {
+        ["day6", "report", what @ ..] => day6::report(what),
+        ["day7", query @ ..] => day7::query(query),
+        ["day8", "debug"] => day8::debug("input/2020/day8.txt"),
+        ["day8", "debug", path] => day8::debug(path),
+        ["day8", "analyze"] => day8::analyze("input/2020/day8.txt", false),
+        ["day8", "analyze", path] => day8::analyze(path, false),
+        ["day8", "dot"] => day8::analyze("input/2020/day8.txt", true),
+        ["day8", "dot", path] => day8::analyze(path, true),
+        ["day11", "frames", options @ ..] => day11::visualize(options),
+        ["day12", "trace", options @ ..] => day12::report(options),
+        _ => println!("advent of code 2020"),
+    }
+}